@@ -0,0 +1,41 @@
+use core::time::Duration;
+
+use super::FeedbackController;
+
+/// Full output one way or the other. Inside the tolerance the last output is
+/// held so the controller doesn't chatter around the target. For a flywheel,
+/// `low` is usually zero so it coasts down instead of braking.
+#[derive(Clone, Copy)]
+pub struct BangBang {
+    high: f64,
+    low: f64,
+    tolerance: f64,
+    output: f64,
+}
+
+impl BangBang {
+    pub const fn new(high: f64, low: f64, tolerance: f64) -> Self {
+        Self {
+            high,
+            low,
+            tolerance,
+            output: 0.0,
+        }
+    }
+}
+
+impl FeedbackController for BangBang {
+    fn output(&mut self, error: f64, _dt: Duration) -> f64 {
+        if error > self.tolerance {
+            self.output = self.high;
+        } else if error < -self.tolerance {
+            self.output = self.low;
+        }
+
+        self.output
+    }
+
+    fn reset(&mut self) {
+        self.output = 0.0;
+    }
+}
@@ -0,0 +1,49 @@
+use core::time::Duration;
+
+use super::{FeedbackController, pid::Pid};
+
+/// Two loops in series. The outer loop turns the error into a target rate and
+/// the inner loop drives the measured rate to it, so the inner loop can hold
+/// back a fast approach that a single PID would overshoot. The rate is how
+/// fast the measurement changed since the last update.
+#[derive(Clone, Copy)]
+pub struct Cascade<O: FeedbackController = Pid, I: FeedbackController = Pid> {
+    outer: O,
+    inner: I,
+    prev_measurement: Option<f64>,
+}
+
+impl<O: FeedbackController, I: FeedbackController> Cascade<O, I> {
+    pub const fn new(outer: O, inner: I) -> Self {
+        Self {
+            outer,
+            inner,
+            prev_measurement: None,
+        }
+    }
+}
+
+impl<O: FeedbackController, I: FeedbackController> FeedbackController for Cascade<O, I> {
+    fn output(&mut self, error: f64, dt: Duration) -> f64 {
+        self.update(0.0, -error, dt)
+    }
+
+    fn update(&mut self, setpoint: f64, measurement: f64, dt: Duration) -> f64 {
+        let target_rate = self.outer.update(setpoint, measurement, dt);
+
+        let seconds = dt.as_secs_f64();
+        let rate = self
+            .prev_measurement
+            .filter(|_| seconds > 0.0)
+            .map_or(0.0, |prev| (measurement - prev) / seconds);
+        self.prev_measurement = Some(measurement);
+
+        self.inner.update(target_rate, rate, dt)
+    }
+
+    fn reset(&mut self) {
+        self.outer.reset();
+        self.inner.reset();
+        self.prev_measurement = None;
+    }
+}
@@ -0,0 +1,170 @@
+use crate::utils::gaussian_elimination;
+
+/// One measurement for fitting a feedforward. Velocity is in RPM and
+/// acceleration in RPM per second, the same units `output` takes.
+#[derive(Clone, Copy)]
+pub struct FeedforwardSample {
+    pub voltage: f64,
+    pub velocity: f64,
+    pub acceleration: f64,
+}
+
+#[derive(Clone, Copy)]
+pub struct Feedforward {
+    ks: f64,
+    kv: f64,
+    ka: f64,
+}
+
+impl Feedforward {
+    // samples slower than this are mostly static friction and sensor noise
+    const MIN_VELOCITY: f64 = 1.0;
+
+    pub const fn new(ks: f64, kv: f64, ka: f64) -> Self {
+        Self { ks, kv, ka }
+    }
+
+    /// Fits `voltage = ks * sign(velocity) + kv * velocity + ka * acceleration`
+    /// to the samples with ordinary least squares. Needs samples both at
+    /// steady speeds and while accelerating to tell `kv` and `ka` apart, `None`
+    /// if they can't be.
+    pub fn fit(samples: &[FeedforwardSample]) -> Option<Self> {
+        let mut system = vec![vec![0.0; 4]; 3];
+        for sample in samples
+            .iter()
+            .filter(|sample| sample.velocity.abs() >= Self::MIN_VELOCITY)
+        {
+            let x = [
+                sample.velocity.signum(),
+                sample.velocity,
+                sample.acceleration,
+            ];
+            for row in 0..3 {
+                for column in 0..3 {
+                    system[row][column] += x[row] * x[column];
+                }
+                system[row][3] += x[row] * sample.voltage;
+            }
+        }
+
+        let values = gaussian_elimination(system)?;
+        Some(Self::new(values[0], values[1], values[2]))
+    }
+
+    /// How much of the variation in voltage the constants explain, from 0 to 1.
+    /// Only counts the samples `fit` uses, `None` if there are none or their
+    /// voltage never varies.
+    pub fn r_squared(&self, samples: &[FeedforwardSample]) -> Option<f64> {
+        let samples: Vec<_> = samples
+            .iter()
+            .filter(|sample| sample.velocity.abs() >= Self::MIN_VELOCITY)
+            .collect();
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mean = samples.iter().map(|sample| sample.voltage).sum::<f64>() / samples.len() as f64;
+
+        let (mut residual, mut total) = (0.0, 0.0);
+        for sample in samples {
+            let predicted = self.output(sample.velocity, sample.acceleration);
+            residual += (sample.voltage - predicted).powi(2);
+            total += (sample.voltage - mean).powi(2);
+        }
+
+        (total > 0.0).then(|| 1.0 - residual / total)
+    }
+
+    pub fn ks(&self) -> f64 {
+        self.ks
+    }
+
+    pub fn kv(&self) -> f64 {
+        self.kv
+    }
+
+    pub fn ka(&self) -> f64 {
+        self.ka
+    }
+
+    pub fn output(&self, velocity: f64, acceleration: f64) -> f64 {
+        let static_friction = if velocity == 0.0 {
+            0.0
+        } else {
+            self.ks * velocity.signum()
+        };
+
+        static_friction + self.kv * velocity + self.ka * acceleration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Feedforward, FeedforwardSample};
+
+    const KS: f64 = 0.8;
+    const KV: f64 = 0.02;
+    const KA: f64 = 0.004;
+
+    // a slow ramp and a step each way, with voltages from known constants
+    fn samples() -> Vec<FeedforwardSample> {
+        let truth = Feedforward::new(KS, KV, KA);
+        let mut samples = Vec::new();
+        for direction in [1.0, -1.0] {
+            for i in 0..50 {
+                let velocity = direction * 10.0 * i as f64;
+                let acceleration = direction * (20.0 + 40.0 * (i % 5) as f64);
+                samples.push(FeedforwardSample {
+                    voltage: truth.output(velocity, acceleration),
+                    velocity,
+                    acceleration,
+                });
+            }
+        }
+
+        samples
+    }
+
+    fn assert_close(name: &str, actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {name} {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn fit_recovers_known_constants() {
+        let feedforward = Feedforward::fit(&samples()).unwrap();
+
+        assert_close("ks", feedforward.ks(), KS);
+        assert_close("kv", feedforward.kv(), KV);
+        assert_close("ka", feedforward.ka(), KA);
+        assert_close("r²", feedforward.r_squared(&samples()).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn slow_samples_are_ignored() {
+        let mut samples = samples();
+        // stiction holding the motor still, which the model can't explain
+        samples.extend((0..20).map(|_| FeedforwardSample {
+            voltage: 0.5,
+            velocity: 0.0,
+            acceleration: 0.0,
+        }));
+
+        let feedforward = Feedforward::fit(&samples).unwrap();
+        assert_close("ks", feedforward.ks(), KS);
+    }
+
+    #[test]
+    fn nothing_to_fit_without_motion() {
+        let still = [FeedforwardSample {
+            voltage: 0.5,
+            velocity: 0.2,
+            acceleration: 0.0,
+        }; 10];
+
+        assert!(Feedforward::fit(&still).is_none());
+        assert_eq!(Feedforward::new(KS, KV, KA).r_squared(&still), None);
+    }
+}
@@ -0,0 +1,95 @@
+use core::time::Duration;
+
+use super::{FeedbackController, pid::Pid};
+
+/// Which error the gains are looked up from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleKey {
+    /// The error at the start of a motion, so the gains are held for the whole
+    /// motion like a PID tuned for that size.
+    /// Profiled motions start with no error, so they should use the current
+    /// error instead.
+    StartingError,
+    /// The error on every update, so the gains change as the robot closes in.
+    CurrentError,
+}
+
+/// A PID whose gains are interpolated from a table of `(error, kp, ki, kd)`
+/// breakpoints instead of one set tuned for every motion size. Errors are in
+/// whatever units the motion hands the controller, radians for turns and meters
+/// for drives, and are compared by magnitude. Errors outside of the table use
+/// the closest breakpoint.
+#[derive(Clone)]
+pub struct GainSchedule {
+    pid: Pid,
+    breakpoints: Vec<(f64, f64, f64, f64)>,
+    key: ScheduleKey,
+    // whether the gains for this motion have been picked
+    scheduled: bool,
+}
+
+impl GainSchedule {
+    /// `base` carries the integral threshold, anti-windup, limits and
+    /// filtering. Its gains are replaced by the schedule's.
+    pub fn new(base: Pid, key: ScheduleKey, breakpoints: &[(f64, f64, f64, f64)]) -> Self {
+        let mut breakpoints = breakpoints.to_vec();
+        breakpoints.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Self {
+            pid: base,
+            breakpoints,
+            key,
+            scheduled: false,
+        }
+    }
+
+    /// The interpolated gains for an error.
+    pub fn gains(&self, error: f64) -> Option<(f64, f64, f64)> {
+        let error = error.abs();
+        let first = self.breakpoints.first()?;
+        let last = self.breakpoints.last()?;
+
+        if error <= first.0 {
+            return Some((first.1, first.2, first.3));
+        }
+        if error >= last.0 {
+            return Some((last.1, last.2, last.3));
+        }
+
+        let next = self.breakpoints.partition_point(|point| point.0 <= error);
+        let (from, to) = (self.breakpoints[next - 1], self.breakpoints[next]);
+        let t = (error - from.0) / (to.0 - from.0);
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+
+        Some((lerp(from.1, to.1), lerp(from.2, to.2), lerp(from.3, to.3)))
+    }
+}
+
+impl FeedbackController for GainSchedule {
+    fn output(&mut self, error: f64, dt: Duration) -> f64 {
+        self.update(0.0, -error, dt)
+    }
+
+    fn update(&mut self, setpoint: f64, measurement: f64, dt: Duration) -> f64 {
+        let reschedule = match self.key {
+            ScheduleKey::StartingError => !self.scheduled,
+            ScheduleKey::CurrentError => true,
+        };
+
+        if reschedule {
+            if let Some((kp, ki, kd)) = self.gains(setpoint - measurement) {
+                self.pid.set_gains(kp, ki, kd);
+            }
+            self.scheduled = true;
+        }
+
+        self.pid.update(setpoint, measurement, dt)
+    }
+
+    /// Starts a new motion, the next update picks the gains again when keyed
+    /// on the starting error.
+    fn reset(&mut self) {
+        self.pid.reset();
+        self.scheduled = false;
+    }
+}
@@ -1 +1,23 @@
+pub mod bang_bang;
+pub mod cascade;
+pub mod feedforward;
+pub mod gain_schedule;
 pub mod pid;
+pub mod take_back_half;
+
+use core::time::Duration;
+
+/// A control law the motions can run on. Each call gets the time since the
+/// last one, and `reset` is called at the start of every motion so nothing
+/// carries over from the one before.
+pub trait FeedbackController {
+    fn output(&mut self, error: f64, dt: Duration) -> f64;
+
+    /// For controllers that treat the setpoint and the measurement differently
+    /// instead of only looking at their difference.
+    fn update(&mut self, setpoint: f64, measurement: f64, dt: Duration) -> f64 {
+        self.output(setpoint - measurement, dt)
+    }
+
+    fn reset(&mut self);
+}
@@ -1,41 +1,171 @@
 use core::time::Duration;
 
+use super::FeedbackController;
+
+/// How the integral is kept from winding up while the error is large or the
+/// output can't go any further.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AntiWindup {
+    /// Clears the integral while the error is outside of the integral
+    /// threshold and whenever the error changes sign.
+    Threshold,
+    /// Stops integrating while the output is clamped and the error would push
+    /// it further past the limit.
+    Conditional,
+    /// Bleeds off the integral by how far the output was clamped, scaled by
+    /// the tracking gain. Higher gains unwind faster.
+    BackCalculation(f64),
+}
+
 #[derive(Clone, Copy)]
 pub struct Pid {
     kp: f64,
     ki: f64,
     kd: f64,
     integral_threshold: f64,
-    prev_error: f64,
+    anti_windup: AntiWindup,
+    output_limit: f64,
+    // seconds, zero leaves the derivative unfiltered
+    derivative_filter: f64,
+    setpoint_weight: f64,
+    derivative_weight: f64,
+
+    // the integral term, already scaled by ki
     integral: f64,
+    prev_error: f64,
+    // none right after a reset so the first output has no derivative kick
+    prev_derivative_input: Option<f64>,
+    derivative: f64,
 }
 
 impl Pid {
+    /// Works like a textbook PID until the builders below change it: no output
+    /// limit, an unfiltered derivative of the error and the integral cleared
+    /// outside of `integral_threshold`.
     pub const fn new(kp: f64, ki: f64, kd: f64, integral_threshold: f64) -> Self {
         Self {
             kp,
             ki,
             kd,
             integral_threshold,
+            anti_windup: AntiWindup::Threshold,
+            output_limit: f64::INFINITY,
+            derivative_filter: 0.0,
+            setpoint_weight: 1.0,
+            derivative_weight: 1.0,
             integral: 0.0,
             prev_error: 0.0,
+            prev_derivative_input: None,
+            derivative: 0.0,
         }
     }
 
-    pub fn output(&mut self, error: f64, dt: Duration) -> f64 {
-        if error.abs() < self.integral_threshold {
-            self.integral += error * dt.as_secs_f64();
-        } else {
-            self.integral = 0.0;
-        }
+    /// Clamps the output to `-limit..=limit`, the limit must be positive.
+    pub const fn output_limit(mut self, limit: f64) -> Self {
+        self.output_limit = limit;
+        self
+    }
+
+    pub const fn anti_windup(mut self, anti_windup: AntiWindup) -> Self {
+        self.anti_windup = anti_windup;
+        self
+    }
+
+    /// Low-passes the derivative with the given time constant to keep sensor
+    /// noise out of the output.
+    pub const fn derivative_filter(mut self, time_constant: Duration) -> Self {
+        self.derivative_filter = time_constant.as_secs_f64();
+        self
+    }
+
+    /// How much of the setpoint the proportional and derivative terms see,
+    /// from 0 to 1. Lower weights soften the response to setpoint changes
+    /// without changing how disturbances are rejected. Only applies through
+    /// `update`.
+    pub const fn setpoint_weights(mut self, proportional: f64, derivative: f64) -> Self {
+        self.setpoint_weight = proportional;
+        self.derivative_weight = derivative;
+        self
+    }
+
+    /// Takes the derivative of the measurement alone so that setpoint changes
+    /// don't kick the output. Only applies through `update`.
+    pub const fn derivative_on_measurement(mut self) -> Self {
+        self.derivative_weight = 0.0;
+        self
+    }
+
+    /// Swaps the gains without touching the controller's state. The integral
+    /// is stored already scaled by `ki`, so changing it doesn't jump the
+    /// output.
+    pub fn set_gains(&mut self, kp: f64, ki: f64, kd: f64) {
+        self.kp = kp;
+        self.ki = ki;
+        self.kd = kd;
+    }
+}
+
+impl FeedbackController for Pid {
+    /// Output for an error alone. Setpoint weighting and derivative on
+    /// measurement need the setpoint separately, use `update` for those.
+    fn output(&mut self, error: f64, dt: Duration) -> f64 {
+        self.update(0.0, -error, dt)
+    }
+
+    fn update(&mut self, setpoint: f64, measurement: f64, dt: Duration) -> f64 {
+        let dt = dt.as_secs_f64();
+        let error = setpoint - measurement;
+
+        let proportional = self.kp * (self.setpoint_weight * setpoint - measurement);
 
-        if error.signum() != self.prev_error.signum() {
-            self.integral = 0.0;
+        let derivative_input = self.derivative_weight * setpoint - measurement;
+        if let Some(prev) = self.prev_derivative_input.filter(|_| dt > 0.0) {
+            let raw = (derivative_input - prev) / dt;
+            let alpha = dt / (self.derivative_filter + dt);
+            self.derivative += alpha * (raw - self.derivative);
         }
+        self.prev_derivative_input = Some(derivative_input);
+        let derivative = self.kd * self.derivative;
 
-        let derivative = (error - self.prev_error) / dt.as_secs_f64();
+        match self.anti_windup {
+            AntiWindup::Threshold => {
+                if error.abs() < self.integral_threshold {
+                    self.integral += self.ki * error * dt;
+                } else {
+                    self.integral = 0.0;
+                }
+
+                if error.signum() != self.prev_error.signum() {
+                    self.integral = 0.0;
+                }
+            }
+            AntiWindup::Conditional => {
+                let unclamped = proportional + self.integral + derivative;
+                let winding_up =
+                    unclamped.abs() >= self.output_limit && error.signum() == unclamped.signum();
+                if !winding_up {
+                    self.integral += self.ki * error * dt;
+                }
+            }
+            AntiWindup::BackCalculation(_) => self.integral += self.ki * error * dt,
+        }
         self.prev_error = error;
 
-        error * self.kp + self.integral * self.ki + derivative * self.kd
+        let unclamped = proportional + self.integral + derivative;
+        let output = unclamped.clamp(-self.output_limit, self.output_limit);
+
+        if let AntiWindup::BackCalculation(gain) = self.anti_windup {
+            self.integral += gain * (output - unclamped) * dt;
+        }
+
+        output
+    }
+
+    /// Forgets everything from previous motions.
+    fn reset(&mut self) {
+        self.integral = 0.0;
+        self.prev_error = 0.0;
+        self.prev_derivative_input = None;
+        self.derivative = 0.0;
     }
 }
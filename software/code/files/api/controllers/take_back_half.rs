@@ -0,0 +1,98 @@
+use core::time::Duration;
+
+use super::FeedbackController;
+
+/// Integrates the error into the output, and each time the error crosses zero
+/// cuts the output to halfway between where it is and where it was at the last
+/// crossing. Settles on the output that holds the target, which makes it a good
+/// fit for velocity control.
+#[derive(Clone, Copy)]
+pub struct TakeBackHalf {
+    gain: f64,
+    limit: f64,
+    // where the output was at the last crossing
+    take_back: f64,
+    // the first crossing takes back toward this guess instead of zero
+    initial: f64,
+    output: f64,
+    // `None` until there's an error to compare against, so the first one can't
+    // look like a crossing
+    prev_error: Option<f64>,
+}
+
+impl TakeBackHalf {
+    /// The output is clamped to `-limit..=limit`.
+    pub const fn new(gain: f64, limit: f64) -> Self {
+        Self {
+            gain,
+            limit,
+            take_back: 0.0,
+            initial: 0.0,
+            output: 0.0,
+            prev_error: None,
+        }
+    }
+
+    /// The output expected to hold the target, used for the first crossing.
+    pub const fn initial_guess(mut self, output: f64) -> Self {
+        self.initial = output;
+        self.take_back = output;
+        self
+    }
+}
+
+impl FeedbackController for TakeBackHalf {
+    fn output(&mut self, error: f64, dt: Duration) -> f64 {
+        self.output =
+            (self.output + self.gain * error * dt.as_secs_f64()).clamp(-self.limit, self.limit);
+
+        if self
+            .prev_error
+            .is_some_and(|prev_error| error.signum() != prev_error.signum())
+        {
+            self.output = (self.output + self.take_back) / 2.0;
+            self.take_back = self.output;
+        }
+        self.prev_error = Some(error);
+
+        self.output
+    }
+
+    fn reset(&mut self) {
+        self.take_back = self.initial;
+        self.output = 0.0;
+        self.prev_error = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::TakeBackHalf;
+    use crate::controllers::FeedbackController;
+
+    const DT: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn first_error_is_not_a_crossing() {
+        let mut controller = TakeBackHalf::new(100.0, 12.0).initial_guess(6.0);
+
+        // only integrated, not averaged with the guess
+        assert_eq!(controller.output(-2.0, DT), -2.0);
+
+        controller.reset();
+        assert_eq!(controller.output(-2.0, DT), -2.0);
+    }
+
+    #[test]
+    fn crossing_takes_back_half() {
+        let mut controller = TakeBackHalf::new(100.0, 12.0).initial_guess(6.0);
+
+        assert_eq!(controller.output(4.0, DT), 4.0);
+        // 4 - 1 integrated, then halfway back to the guess
+        assert_eq!(controller.output(-1.0, DT), 4.5);
+        // the next crossing takes back toward the last one
+        assert_eq!(controller.output(1.0, DT), 5.0);
+    }
+}
@@ -0,0 +1,176 @@
+use core::fmt::Debug;
+use std::{cell::RefCell, future::Future, rc::Rc};
+
+use uom::si::{
+    angle::radian,
+    f64::{Angle, Length},
+    length::millimeter,
+};
+use vexide::{
+    math::Angle as VAngle,
+    prelude::{AdiDigitalOut, AdiEncoder, DistanceSensor, InertialSensor, Motor, OpticalSensor},
+    smart::imu::InertialError,
+};
+
+// Hardware traits that the rest of the API is written against. Each one has an
+// implementation for the matching vexide device and a mock in `hardware::mock`
+// so that the logic built on top of them can run without a brain.
+
+pub trait MotorDevice {
+    fn set_voltage(&mut self, voltage: f64);
+
+    /// Sets the target velocity in RPM using the motor's internal controller.
+    fn set_velocity(&mut self, velocity: f64);
+
+    fn voltage(&self) -> Option<f64>;
+
+    /// The measured velocity in RPM.
+    fn velocity(&self) -> Option<f64>;
+
+    fn position(&self) -> Option<Angle>;
+}
+
+pub trait InertialDevice {
+    type Error: Debug;
+
+    fn calibrate(&mut self) -> impl Future<Output = Result<(), Self::Error>>;
+
+    /// Sets the rotation using the sensor's clockwise convention.
+    fn set_rotation(&mut self, rotation: Angle);
+
+    /// The unbounded rotation using the sensor's clockwise convention.
+    fn rotation(&self) -> Option<Angle>;
+}
+
+pub trait EncoderDevice {
+    fn position(&self) -> Option<Angle>;
+}
+
+pub trait OpticalDevice {
+    fn hue(&self) -> Option<f64>;
+
+    fn proximity(&self) -> Option<f64>;
+}
+
+pub trait DistanceDevice {
+    /// Distance to the detected object, if there is one.
+    fn distance(&self) -> Option<Length>;
+}
+
+pub trait DigitalOutDevice {
+    fn set_high(&mut self);
+
+    fn set_low(&mut self);
+
+    fn toggle(&mut self);
+
+    fn is_high(&self) -> bool;
+}
+
+fn to_uom(angle: VAngle) -> Angle {
+    Angle::new::<radian>(angle.as_radians())
+}
+
+impl MotorDevice for Motor {
+    fn set_voltage(&mut self, voltage: f64) {
+        _ = Motor::set_voltage(self, voltage);
+    }
+
+    fn set_velocity(&mut self, velocity: f64) {
+        _ = Motor::set_velocity(self, velocity as i32);
+    }
+
+    fn voltage(&self) -> Option<f64> {
+        Motor::voltage(self).ok()
+    }
+
+    fn velocity(&self) -> Option<f64> {
+        Motor::velocity(self).ok()
+    }
+
+    fn position(&self) -> Option<Angle> {
+        Motor::position(self).ok().map(to_uom)
+    }
+}
+
+// Shared handle so the same motors can drive the robot and feed odometry.
+impl<M: MotorDevice> MotorDevice for Rc<RefCell<M>> {
+    fn set_voltage(&mut self, voltage: f64) {
+        self.borrow_mut().set_voltage(voltage);
+    }
+
+    fn set_velocity(&mut self, velocity: f64) {
+        self.borrow_mut().set_velocity(velocity);
+    }
+
+    fn voltage(&self) -> Option<f64> {
+        self.borrow().voltage()
+    }
+
+    fn velocity(&self) -> Option<f64> {
+        self.borrow().velocity()
+    }
+
+    fn position(&self) -> Option<Angle> {
+        self.borrow().position()
+    }
+}
+
+impl InertialDevice for InertialSensor {
+    type Error = InertialError;
+
+    fn calibrate(&mut self) -> impl Future<Output = Result<(), Self::Error>> {
+        InertialSensor::calibrate(self)
+    }
+
+    fn set_rotation(&mut self, rotation: Angle) {
+        _ = InertialSensor::set_rotation(self, VAngle::from_radians(rotation.get::<radian>()));
+    }
+
+    fn rotation(&self) -> Option<Angle> {
+        InertialSensor::rotation(self).ok().map(to_uom)
+    }
+}
+
+impl<const TPR: u32> EncoderDevice for AdiEncoder<TPR> {
+    fn position(&self) -> Option<Angle> {
+        AdiEncoder::position(self).ok().map(to_uom)
+    }
+}
+
+impl OpticalDevice for OpticalSensor {
+    fn hue(&self) -> Option<f64> {
+        OpticalSensor::hue(self).ok()
+    }
+
+    fn proximity(&self) -> Option<f64> {
+        OpticalSensor::proximity(self).ok()
+    }
+}
+
+impl DistanceDevice for DistanceSensor {
+    fn distance(&self) -> Option<Length> {
+        self.object()
+            .ok()
+            .flatten()
+            .map(|object| Length::new::<millimeter>(object.distance as f64))
+    }
+}
+
+impl DigitalOutDevice for AdiDigitalOut {
+    fn set_high(&mut self) {
+        _ = AdiDigitalOut::set_high(self);
+    }
+
+    fn set_low(&mut self) {
+        _ = AdiDigitalOut::set_low(self);
+    }
+
+    fn toggle(&mut self) {
+        _ = AdiDigitalOut::toggle(self);
+    }
+
+    fn is_high(&self) -> bool {
+        self.level().is_ok_and(|level| level.is_high())
+    }
+}
@@ -2,16 +2,16 @@ use std::f64::consts::TAU;
 
 use log::{error, info};
 use uom::si::{angle::radian, f64::Angle};
-use vexide::{math::Angle as VAngle, prelude::InertialSensor};
+use vexide::prelude::InertialSensor;
 
-use super::average;
+use super::{average, device::InertialDevice};
 
-pub struct Imu {
-    imus: Vec<InertialSensor>,
+pub struct Imu<I: InertialDevice = InertialSensor> {
+    imus: Vec<I>,
 }
 
-impl Imu {
-    pub fn new(imus: Vec<InertialSensor>) -> Self {
+impl<I: InertialDevice> Imu<I> {
+    pub fn new(imus: Vec<I>) -> Self {
         Self { imus }
     }
 
@@ -26,15 +26,15 @@ impl Imu {
 
     pub fn set_heading(&mut self, heading: Angle) {
         for imu in self.imus.iter_mut() {
-            _ = imu.set_rotation(VAngle::from_radians(heading.get::<radian>()));
+            imu.set_rotation(heading);
         }
     }
 
     pub fn rotation(&self) -> Angle {
         let mut angles = Vec::new();
         for imu in self.imus.iter() {
-            if let Ok(rotation) = imu.rotation() {
-                angles.push(TAU - rotation.as_radians());
+            if let Some(rotation) = imu.rotation() {
+                angles.push(TAU - rotation.get::<radian>());
             }
         }
 
@@ -44,11 +44,57 @@ impl Imu {
     pub fn heading(&self) -> Angle {
         let mut angles = Vec::new();
         for imu in self.imus.iter() {
-            if let Ok(rotation) = imu.rotation() {
-                angles.push(TAU - rotation.as_radians());
+            if let Some(rotation) = imu.rotation() {
+                angles.push(TAU - rotation.get::<radian>());
             }
         }
 
         Angle::new::<radian>(average(angles).rem_euclid(TAU))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+    use uom::si::{angle::radian, f64::Angle};
+
+    use super::Imu;
+    use crate::hardware::mock::MockImu;
+
+    const TOLERANCE: f64 = 1e-9;
+
+    #[test]
+    fn clockwise_readings_become_counterclockwise() {
+        let sensor = MockImu::new();
+        let imu = Imu::new(vec![sensor.clone()]);
+
+        sensor.set_measured(Angle::new::<radian>(FRAC_PI_2));
+
+        assert!((imu.rotation().get::<radian>() - (TAU - FRAC_PI_2)).abs() < TOLERANCE);
+        assert!((imu.heading().get::<radian>() - (TAU - FRAC_PI_2)).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn heading_wraps_but_rotation_does_not() {
+        let sensor = MockImu::new();
+        let imu = Imu::new(vec![sensor.clone()]);
+
+        // a quarter turn counterclockwise
+        sensor.set_measured(Angle::new::<radian>(-FRAC_PI_2));
+
+        assert!((imu.rotation().get::<radian>() - (TAU + FRAC_PI_2)).abs() < TOLERANCE);
+        assert!((imu.heading().get::<radian>() - FRAC_PI_2).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn sensors_are_averaged() {
+        let sensors = [MockImu::new(), MockImu::new()];
+        let imu = Imu::new(sensors.to_vec());
+
+        sensors[0].set_measured(Angle::new::<radian>(PI - 0.1));
+        sensors[1].set_measured(Angle::new::<radian>(PI + 0.1));
+
+        assert!((imu.heading().get::<radian>() - PI).abs() < TOLERANCE);
+    }
+}
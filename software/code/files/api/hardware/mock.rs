@@ -0,0 +1,209 @@
+use core::convert::Infallible;
+use std::{cell::RefCell, rc::Rc};
+
+use uom::{
+    ConstZero,
+    si::f64::{Angle, Length},
+};
+
+use super::device::{
+    DigitalOutDevice, DistanceDevice, EncoderDevice, InertialDevice, MotorDevice, OpticalDevice,
+};
+
+// In-memory devices for running the API off of the robot. Every mock is a cheap
+// handle to shared state, so a test or simulator can keep a clone and read what
+// was commanded or write what the "sensor" should report.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MotorState {
+    pub voltage: f64,
+    /// Set when the motor was last given a velocity instead of a voltage.
+    pub target_velocity: Option<f64>,
+    pub velocity: f64,
+    pub position: Angle,
+}
+
+impl Default for MotorState {
+    fn default() -> Self {
+        Self {
+            voltage: 0.0,
+            target_velocity: None,
+            velocity: 0.0,
+            position: Angle::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MockMotor {
+    state: Rc<RefCell<MotorState>>,
+}
+
+impl MockMotor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> MotorState {
+        *self.state.borrow()
+    }
+
+    pub fn set_measured(&self, velocity: f64, position: Angle) {
+        let mut state = self.state.borrow_mut();
+        state.velocity = velocity;
+        state.position = position;
+    }
+}
+
+impl MotorDevice for MockMotor {
+    fn set_voltage(&mut self, voltage: f64) {
+        let mut state = self.state.borrow_mut();
+        state.voltage = voltage;
+        state.target_velocity = None;
+    }
+
+    fn set_velocity(&mut self, velocity: f64) {
+        self.state.borrow_mut().target_velocity = Some(velocity);
+    }
+
+    fn voltage(&self) -> Option<f64> {
+        Some(self.state.borrow().voltage)
+    }
+
+    fn velocity(&self) -> Option<f64> {
+        Some(self.state.borrow().velocity)
+    }
+
+    fn position(&self) -> Option<Angle> {
+        Some(self.state.borrow().position)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MockImu {
+    rotation: Rc<RefCell<Angle>>,
+}
+
+impl MockImu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the reported rotation using the sensor's clockwise convention.
+    pub fn set_measured(&self, rotation: Angle) {
+        self.rotation.replace(rotation);
+    }
+}
+
+impl InertialDevice for MockImu {
+    type Error = Infallible;
+
+    async fn calibrate(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_rotation(&mut self, rotation: Angle) {
+        self.rotation.replace(rotation);
+    }
+
+    fn rotation(&self) -> Option<Angle> {
+        Some(*self.rotation.borrow())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MockEncoder {
+    position: Rc<RefCell<Angle>>,
+}
+
+impl MockEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_measured(&self, position: Angle) {
+        self.position.replace(position);
+    }
+}
+
+impl EncoderDevice for MockEncoder {
+    fn position(&self) -> Option<Angle> {
+        Some(*self.position.borrow())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MockOptical {
+    reading: Rc<RefCell<(f64, f64)>>,
+}
+
+impl MockOptical {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_measured(&self, hue: f64, proximity: f64) {
+        self.reading.replace((hue, proximity));
+    }
+}
+
+impl OpticalDevice for MockOptical {
+    fn hue(&self) -> Option<f64> {
+        Some(self.reading.borrow().0)
+    }
+
+    fn proximity(&self) -> Option<f64> {
+        Some(self.reading.borrow().1)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MockDistance {
+    distance: Rc<RefCell<Option<Length>>>,
+}
+
+impl MockDistance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_measured(&self, distance: Option<Length>) {
+        self.distance.replace(distance);
+    }
+}
+
+impl DistanceDevice for MockDistance {
+    fn distance(&self) -> Option<Length> {
+        *self.distance.borrow()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MockDigitalOut {
+    high: Rc<RefCell<bool>>,
+}
+
+impl MockDigitalOut {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DigitalOutDevice for MockDigitalOut {
+    fn set_high(&mut self) {
+        self.high.replace(true);
+    }
+
+    fn set_low(&mut self) {
+        self.high.replace(false);
+    }
+
+    fn toggle(&mut self) {
+        let high = *self.high.borrow();
+        self.high.replace(!high);
+    }
+
+    fn is_high(&self) -> bool {
+        *self.high.borrow()
+    }
+}
@@ -1,6 +1,9 @@
+pub mod device;
 pub mod imu;
+pub mod mock;
 pub mod motor_group;
 pub mod otos;
+pub mod rangefinder;
 pub mod tracking_wheel;
 
 mod packet;
@@ -1,17 +1,21 @@
 use std::time::Duration;
 
+use uom::si::{angle::radian, f64::Angle};
 use vexide::prelude::Motor;
 
-use super::average;
-use crate::controllers::pid::Pid;
+use super::{
+    average,
+    device::{EncoderDevice, MotorDevice},
+};
+use crate::controllers::{FeedbackController, feedforward::Feedforward, pid::Pid};
 
-pub struct MotorGroup {
-    motors: Vec<Motor>,
-    motor_controller: Option<MotorController>,
+pub struct MotorGroup<M: MotorDevice = Motor, C: FeedbackController = Pid> {
+    motors: Vec<M>,
+    motor_controller: Option<MotorController<C>>,
 }
 
-impl MotorGroup {
-    pub fn new(motors: Vec<Motor>, motor_controller: Option<MotorController>) -> Self {
+impl<M: MotorDevice, C: FeedbackController> MotorGroup<M, C> {
+    pub fn new(motors: Vec<M>, motor_controller: Option<MotorController<C>>) -> Self {
         Self {
             motors,
             motor_controller,
@@ -20,20 +24,29 @@ impl MotorGroup {
 
     pub fn set_voltage(&mut self, voltage: f64) {
         for motor in self.motors.iter_mut() {
-            _ = motor.set_voltage(voltage);
+            motor.set_voltage(voltage);
         }
     }
 
     pub fn set_velocity(&mut self, velocity: f64) {
-        for motor in self.motors.iter_mut() {
-            match self.motor_controller {
-                Some(mut controller) => {
-                    let motor_velocity = motor.velocity().unwrap_or_default();
-                    let voltage = controller.output(velocity, motor_velocity, None);
-                    _ = motor.set_voltage(voltage);
+        match self.motor_controller.as_mut() {
+            Some(controller) => {
+                // one controller for the whole group so its state carries between calls
+                let mut velocities = Vec::new();
+                for motor in self.motors.iter() {
+                    if let Some(velocity) = motor.velocity() {
+                        velocities.push(velocity);
+                    }
                 }
-                None => {
-                    _ = motor.set_velocity(velocity as i32);
+
+                let voltage = controller.output(velocity, average(velocities), None);
+                for motor in self.motors.iter_mut() {
+                    motor.set_voltage(voltage);
+                }
+            }
+            None => {
+                for motor in self.motors.iter_mut() {
+                    motor.set_velocity(velocity);
                 }
             }
         }
@@ -42,7 +55,7 @@ impl MotorGroup {
     pub fn voltage(&self) -> f64 {
         let mut voltages = Vec::new();
         for motor in self.motors.iter() {
-            if let Ok(voltage) = motor.voltage() {
+            if let Some(voltage) = motor.voltage() {
                 voltages.push(voltage);
             }
         }
@@ -53,7 +66,7 @@ impl MotorGroup {
     pub fn velocity(&self) -> f64 {
         let mut velocities = Vec::new();
         for motor in self.motors.iter() {
-            if let Ok(velocity) = motor.velocity() {
+            if let Some(velocity) = motor.velocity() {
                 velocities.push(velocity);
             }
         }
@@ -61,31 +74,103 @@ impl MotorGroup {
         average(velocities) // * ratio
     }
 
-    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, Motor> {
+    pub fn position(&self) -> Angle {
+        let mut positions = Vec::new();
+        for motor in self.motors.iter() {
+            if let Some(position) = motor.position() {
+                positions.push(position.get::<radian>());
+            }
+        }
+
+        Angle::new::<radian>(average(positions))
+    }
+
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, M> {
         self.motors.iter_mut()
     }
 }
 
+// lets the drive motors stand in for tracking wheels
+impl<M: MotorDevice, C: FeedbackController> EncoderDevice for MotorGroup<M, C> {
+    fn position(&self) -> Option<Angle> {
+        let position = MotorGroup::position(self);
+        (!position.is_nan()).then_some(position)
+    }
+}
+
 #[derive(Clone, Copy)]
-pub struct MotorController {
-    pid: Pid,
-    ks: f64,
-    kv: f64,
-    ka: f64,
+pub struct MotorController<C: FeedbackController = Pid> {
+    controller: C,
+    feedforward: Feedforward,
 }
 
-impl MotorController {
-    pub fn new(pid: Pid, ks: f64, kv: f64, ka: f64) -> Self {
-        Self { pid, ks, kv, ka }
+impl<C: FeedbackController> MotorController<C> {
+    pub fn new(controller: C, ks: f64, kv: f64, ka: f64) -> Self {
+        Self {
+            controller,
+            feedforward: Feedforward::new(ks, kv, ka),
+        }
     }
 
     pub fn output(&mut self, target_rpm: f64, actual_rpm: f64, acceleration: Option<f64>) -> f64 {
-        let error = target_rpm - actual_rpm;
-
-        let ff = self.kv + self.ks + acceleration.unwrap_or_default() * self.ka;
+        let ff = self
+            .feedforward
+            .output(target_rpm, acceleration.unwrap_or_default());
         // change duration to a non-const using Instant
-        let pid = self.pid.output(error, Duration::from_millis(10));
+        let feedback = self
+            .controller
+            .update(target_rpm, actual_rpm, Duration::from_millis(10));
+
+        ff + feedback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uom::si::{angle::revolution, f64::Angle};
+
+    use super::MotorGroup;
+    use crate::hardware::mock::MockMotor;
+
+    fn group(motors: &[MockMotor]) -> MotorGroup<MockMotor> {
+        MotorGroup::new(motors.to_vec(), None)
+    }
+
+    #[test]
+    fn voltage_reaches_every_motor() {
+        let motors = [MockMotor::new(), MockMotor::new()];
+        let mut group = group(&motors);
+
+        group.set_voltage(6.0);
+
+        for motor in motors.iter() {
+            assert_eq!(motor.state().voltage, 6.0);
+            assert_eq!(motor.state().target_velocity, None);
+        }
+        assert_eq!(group.voltage(), 6.0);
+    }
+
+    #[test]
+    fn velocity_without_a_controller_uses_the_motors() {
+        let motors = [MockMotor::new(), MockMotor::new()];
+        let mut group = group(&motors);
+
+        group.set_velocity(300.0);
+
+        for motor in motors.iter() {
+            assert_eq!(motor.state().target_velocity, Some(300.0));
+        }
+    }
+
+    #[test]
+    fn readings_are_averaged() {
+        let motors = [MockMotor::new(), MockMotor::new(), MockMotor::new()];
+        motors[0].set_measured(100.0, Angle::new::<revolution>(1.0));
+        motors[1].set_measured(200.0, Angle::new::<revolution>(2.0));
+        motors[2].set_measured(600.0, Angle::new::<revolution>(6.0));
+        let group = group(&motors);
 
-        ff + pid
+        assert!((group.velocity() - 300.0).abs() < 1e-9);
+        assert!((group.position().get::<revolution>() - 3.0).abs() < 1e-9);
     }
 }
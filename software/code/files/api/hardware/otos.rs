@@ -1,8 +1,4 @@
-use std::{
-    cell::RefCell,
-    rc::Rc,
-    time::{Duration, Instant},
-};
+use std::{cell::RefCell, rc::Rc, time::Duration};
 
 use bytemuck::{Pod, Zeroable};
 use log::{error, info};
@@ -16,12 +12,18 @@ use uom::si::{
 use vexide::{
     prelude::SerialPort,
     smart::{SmartPort, serial::SerialError},
-    task::{Task, spawn},
-    time::sleep,
 };
 
 use super::{packet::Packet, serial_device::SerialDevice};
-use crate::localization::pose::Pose;
+use crate::{
+    localization::{
+        history::PoseHistory,
+        localizer::Localizer,
+        pose::Pose,
+        timing::{LoopStats, LoopTimer},
+    },
+    runtime::{Instant, Task, sleep, spawn},
+};
 
 struct Command;
 
@@ -55,11 +57,17 @@ struct OTOSData {
 
 pub struct Otos {
     pose: Rc<RefCell<Pose>>,
-    _task: Task<()>,
+    // raw readings, the offset is applied on the way out
+    history: Rc<RefCell<PoseHistory>>,
+    timer: Rc<RefCell<LoopTimer>>,
+    // places the sensor's readings where `set_pose` last put the robot
+    offset: Pose,
+    _task: Task,
 }
 
 impl Otos {
     const CALIBRATION_TIMEOUT: Duration = Duration::from_secs(1);
+    const HISTORY: Duration = Duration::from_secs(1);
 
     // sending messages to the OTOS requires 2 bytes, the response ID and checksum
     const SENDING_SIZE: usize = 2;
@@ -68,7 +76,7 @@ impl Otos {
     const RECEIVING_SIZE: usize = 14;
 
     #[must_use]
-    pub async fn new(port: SmartPort, start: Pose, offset: Pose) -> Self {
+    pub async fn new(port: SmartPort, start: Pose, offset: Pose, period: Duration) -> Self {
         let port = SerialPort::open(port, 115200).await;
         let mut otos = SerialDevice::new(port, Duration::from_millis(5));
 
@@ -143,9 +151,16 @@ impl Otos {
         Self::check(&mut otos).await;
         info!("OTOS constructed!");
         let pose = Rc::new(RefCell::new(start));
+        let history = Rc::new(RefCell::new(PoseHistory::new(
+            (Self::HISTORY.as_millis() / period.as_millis().max(1)).max(1) as usize,
+        )));
+        let timer = Rc::new(RefCell::new(LoopTimer::new("OTOS", period)));
 
         Self {
             pose: pose.clone(),
+            history: history.clone(),
+            timer: timer.clone(),
+            offset: Pose::default(),
             _task: spawn(async move {
                 sleep(Duration::from_millis(100)).await;
 
@@ -153,32 +168,30 @@ impl Otos {
                     match Self::get_pose(&mut otos).await {
                         Ok(updated_pose) => {
                             pose.replace(updated_pose);
+                            history.borrow_mut().push(Instant::now(), updated_pose);
                         }
                         Err(e) => {
                             error!("Otos failed: {}", e);
                         }
                     }
 
-                    sleep(Duration::from_millis(10)).await;
+                    let wait = timer.borrow_mut().tick();
+                    sleep(wait).await;
                 }
             }),
         }
     }
 
-    pub fn pose(&self) -> Pose {
-        *self.pose.borrow()
-    }
-
     pub fn x(&self) -> Length {
-        self.pose.borrow().x
+        self.pose().x
     }
 
     pub fn y(&self) -> Length {
-        self.pose.borrow().y
+        self.pose().y
     }
 
     pub fn h(&self) -> Angle {
-        self.pose.borrow().h
+        self.pose().h
     }
 
     pub fn vf(&self) -> Velocity {
@@ -193,6 +206,27 @@ impl Otos {
         self.pose.borrow().omega
     }
 
+    /// The newest reading and when it arrived, `None` before the first one.
+    pub fn latest(&self) -> Option<(Instant, Pose)> {
+        self.history
+            .borrow()
+            .newest()
+            .map(|(time, raw)| (time, self.to_field(raw)))
+    }
+
+    fn to_field(&self, raw: Pose) -> Pose {
+        self.offset.compose(raw)
+    }
+
+    pub fn stats(&self) -> LoopStats {
+        self.timer.borrow().stats()
+    }
+
+    /// Logs the loop stats every `interval`, or stops logging them with `None`.
+    pub fn log_stats(&mut self, interval: Option<Duration>) {
+        self.timer.borrow_mut().log_every(interval);
+    }
+
     async fn get_pose(otos: &mut SerialDevice) -> Result<Pose, SerialError> {
         let pos_packet = otos
             .msg(Packet::new(Command::GET_POSITION), Self::RECEIVING_SIZE)
@@ -239,3 +273,20 @@ impl Otos {
         good
     }
 }
+
+impl Localizer for Otos {
+    fn pose(&self) -> Pose {
+        self.to_field(*self.pose.borrow())
+    }
+
+    fn set_pose(&mut self, pose: Pose) {
+        self.offset = pose.compose(self.pose.borrow().inverse());
+    }
+
+    fn pose_at(&self, time: Instant) -> Option<Pose> {
+        self.history
+            .borrow()
+            .pose_at(time)
+            .map(|raw| self.to_field(raw))
+    }
+}
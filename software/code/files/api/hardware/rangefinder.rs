@@ -0,0 +1,64 @@
+use uom::si::{
+    angle::radian,
+    f64::{Angle, Length},
+    length::millimeter,
+};
+use vexide::{prelude::DistanceSensor, smart::SmartPort};
+
+use super::device::DistanceDevice;
+use crate::localization::{pose::Pose, vec2::Vec2};
+
+/// A distance sensor along with where it is mounted on the robot. The offset is
+/// measured from the tracking center and the angle is the direction the sensor
+/// faces relative to the front of the robot, counterclockwise.
+pub struct Rangefinder<D: DistanceDevice = DistanceSensor> {
+    sensor: D,
+    from_center: Vec2<Length>,
+    angle: Angle,
+}
+
+impl Rangefinder {
+    pub fn new(port: SmartPort, from_center: Vec2<Length>, angle: Angle) -> Self {
+        Self::with_sensor(DistanceSensor::new(port), from_center, angle)
+    }
+}
+
+impl<D: DistanceDevice> Rangefinder<D> {
+    // the V5 distance sensor stops reporting reliable readings past 2 meters
+    const MAX_RANGE: f64 = 2000.0;
+
+    pub fn with_sensor(sensor: D, from_center: Vec2<Length>, angle: Angle) -> Self {
+        Self {
+            sensor,
+            from_center,
+            angle,
+        }
+    }
+
+    pub fn from_center(&self) -> Vec2<Length> {
+        self.from_center
+    }
+
+    pub fn angle(&self) -> Angle {
+        self.angle
+    }
+
+    /// Where the sensor is and which way it faces on the field when the robot
+    /// is at `pose`.
+    pub fn ray(&self, pose: Pose) -> (Vec2<Length>, Angle) {
+        let (sin, cos) = pose.h.get::<radian>().sin_cos();
+        let origin = Vec2::new(
+            pose.x + self.from_center.x * cos - self.from_center.y * sin,
+            pose.y + self.from_center.x * sin + self.from_center.y * cos,
+        );
+
+        (origin, pose.h + self.angle)
+    }
+
+    /// The measured distance, or `None` if nothing is in range.
+    pub fn distance(&self) -> Option<Length> {
+        self.sensor
+            .distance()
+            .filter(|distance| distance.get::<millimeter>() <= Self::MAX_RANGE)
+    }
+}
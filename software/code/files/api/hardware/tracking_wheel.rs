@@ -1,15 +1,22 @@
 use std::f64::consts::PI;
 
-use uom::si::{
-    angle::{radian, revolution},
-    f64::{Angle, Length},
+use uom::{
+    ConstZero,
+    si::{
+        angle::revolution,
+        f64::{Angle, Length},
+    },
 };
 use vexide::{adi::AdiPort, math::Direction, prelude::AdiEncoder};
 
+use super::{
+    device::{EncoderDevice, MotorDevice},
+    motor_group::MotorGroup,
+};
 use crate::localization::vec2::Vec2;
 
-pub struct TrackingWheel {
-    encoder: AdiEncoder<4096>,
+pub struct TrackingWheel<E: EncoderDevice = AdiEncoder<4096>> {
+    encoder: E,
     direction: Direction,
     wheel_circum: Length,
     from_center: Vec2<Length>,
@@ -26,9 +33,45 @@ impl TrackingWheel {
         from_center: Vec2<Length>,
         angle: Angle,
     ) -> Self {
-        let encoder = AdiEncoder::new(top_port, bottom_port);
-        let prev_position =
-            Angle::new::<radian>(encoder.position().unwrap_or_default().as_radians());
+        Self::with_encoder(
+            AdiEncoder::new(top_port, bottom_port),
+            direction,
+            wheel_diameter,
+            from_center,
+            angle,
+        )
+    }
+}
+
+impl<M: MotorDevice> TrackingWheel<MotorGroup<M>> {
+    /// Tracks with the drive motors' built-in encoders. `gear_ratio` is wheel
+    /// turns per motor turn and the motors' own directions are kept. The
+    /// drivetrain can share the motors through `Rc<RefCell<Motor>>` handles.
+    pub fn from_motors(
+        motors: MotorGroup<M>,
+        wheel_diameter: Length,
+        gear_ratio: f64,
+        from_center: Vec2<Length>,
+    ) -> Self {
+        Self::with_encoder(
+            motors,
+            Direction::Forward,
+            wheel_diameter * gear_ratio,
+            from_center,
+            Angle::ZERO,
+        )
+    }
+}
+
+impl<E: EncoderDevice> TrackingWheel<E> {
+    pub fn with_encoder(
+        encoder: E,
+        direction: Direction,
+        wheel_diameter: Length,
+        from_center: Vec2<Length>,
+        angle: Angle,
+    ) -> Self {
+        let prev_position = encoder.position().unwrap_or_default();
 
         Self {
             encoder,
@@ -49,15 +92,73 @@ impl TrackingWheel {
     }
 
     pub fn traveled(&mut self) -> Length {
-        let position =
-            Angle::new::<radian>(self.encoder.position().unwrap_or_default().as_radians())
-                * match self.direction {
-                    Direction::Forward => 1.0,
-                    Direction::Reverse => -1.0,
-                };
+        let position = self.encoder.position().unwrap_or_default()
+            * match self.direction {
+                Direction::Forward => 1.0,
+                Direction::Reverse => -1.0,
+            };
         let change = position - self.prev_position;
         self.prev_position = position;
 
         self.wheel_circum * change.get::<revolution>()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use uom::{
+        ConstZero,
+        si::{
+            angle::revolution,
+            f64::{Angle, Length},
+            length::inch,
+        },
+    };
+    use vexide::math::Direction;
+
+    use super::TrackingWheel;
+    use crate::{hardware::mock::MockEncoder, localization::vec2::Vec2};
+
+    fn wheel(encoder: &MockEncoder, direction: Direction) -> TrackingWheel<MockEncoder> {
+        TrackingWheel::with_encoder(
+            encoder.clone(),
+            direction,
+            Length::new::<inch>(2.0),
+            Vec2::new(Length::ZERO, Length::ZERO),
+            Angle::ZERO,
+        )
+    }
+
+    #[test]
+    fn revolutions_become_distance() {
+        let encoder = MockEncoder::new();
+        let mut wheel = wheel(&encoder, Direction::Forward);
+
+        encoder.set_measured(Angle::new::<revolution>(1.5));
+
+        assert!((wheel.traveled().get::<inch>() - 3.0 * PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn traveled_is_the_change_since_the_last_reading() {
+        let encoder = MockEncoder::new();
+        encoder.set_measured(Angle::new::<revolution>(4.0));
+        let mut wheel = wheel(&encoder, Direction::Forward);
+
+        encoder.set_measured(Angle::new::<revolution>(5.0));
+        assert!((wheel.traveled().get::<inch>() - 2.0 * PI).abs() < 1e-9);
+        assert_eq!(wheel.traveled().get::<inch>(), 0.0);
+    }
+
+    #[test]
+    fn reversed_wheels_count_backwards() {
+        let encoder = MockEncoder::new();
+        let mut wheel = wheel(&encoder, Direction::Reverse);
+
+        encoder.set_measured(Angle::new::<revolution>(1.0));
+
+        assert!((wheel.traveled().get::<inch>() + 2.0 * PI).abs() < 1e-9);
+    }
+}
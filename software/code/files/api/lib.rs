@@ -2,9 +2,14 @@ pub mod controllers;
 pub mod hardware;
 pub mod localization;
 pub mod motion;
+pub mod routine;
+pub mod runtime;
+pub mod simulation;
 pub mod subsystems;
 
 pub mod logger;
 pub mod mappings;
+pub mod recording;
+pub mod selector;
 pub mod theme;
 pub mod utils;
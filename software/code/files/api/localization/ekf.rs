@@ -0,0 +1,319 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use uom::si::{
+    angle::radian,
+    f64::{Angle, Length, Time},
+    length::meter,
+    time::second,
+};
+
+use super::{
+    history::PoseHistory,
+    localizer::Localizer,
+    odometry::local_displacement,
+    pose::Pose,
+    timing::{LoopStats, LoopTimer},
+};
+use crate::{
+    hardware::{
+        device::{EncoderDevice, InertialDevice},
+        imu::Imu,
+        otos::Otos,
+        tracking_wheel::TrackingWheel,
+    },
+    runtime::{Instant, Task, sleep, spawn},
+    utils::wrapped,
+};
+
+type Matrix = [[f64; 3]; 3];
+
+const IDENTITY: Matrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// Noise of each source as standard deviations, in meters and radians.
+#[derive(Clone, Copy)]
+pub struct EkfSettings {
+    /// Drift of the wheels per meter traveled.
+    pub translation_noise: f64,
+    /// Drift of the IMU per radian turned.
+    pub rotation_noise: f64,
+    pub otos_position_noise: f64,
+    pub otos_heading_noise: f64,
+}
+
+impl Default for EkfSettings {
+    fn default() -> Self {
+        Self {
+            translation_noise: 0.02,
+            rotation_noise: 0.01,
+            otos_position_noise: 0.02,
+            otos_heading_noise: 0.02,
+        }
+    }
+}
+
+struct State {
+    pose: Pose,
+    covariance: Matrix,
+    // places the OTOS readings in the field frame after the pose is set
+    otos_offset: Pose,
+    // when the last OTOS reading used was taken
+    last_reading: Option<Instant>,
+    history: PoseHistory,
+}
+
+/// Extended Kalman filter that predicts with tracking wheels and the IMU and
+/// corrects with the absolute pose from the OTOS.
+pub struct Ekf {
+    state: Rc<RefCell<State>>,
+    otos: Rc<Otos>,
+    timer: Rc<RefCell<LoopTimer>>,
+    _task: Task,
+}
+
+impl Ekf {
+    const HISTORY: Duration = Duration::from_secs(1);
+
+    pub fn new<E: EncoderDevice + 'static, I: InertialDevice + 'static>(
+        starting_pose: Pose,
+        period: Duration,
+        mut wheel_1: TrackingWheel<E>,
+        mut wheel_2: TrackingWheel<E>,
+        imu: Imu<I>,
+        otos: Otos,
+        settings: EkfSettings,
+    ) -> Self {
+        let otos = Rc::new(otos);
+        let state = Rc::new(RefCell::new(State {
+            pose: starting_pose,
+            covariance: [[0.0; 3]; 3],
+            otos_offset: starting_pose.compose(otos.pose().inverse()),
+            last_reading: None,
+            history: PoseHistory::new(
+                (Self::HISTORY.as_millis() / period.as_millis().max(1)).max(1) as usize,
+            ),
+        }));
+        let timer = Rc::new(RefCell::new(LoopTimer::new("EKF", period)));
+
+        Self {
+            state: state.clone(),
+            otos: otos.clone(),
+            timer: timer.clone(),
+            _task: spawn(async move {
+                let mut prev_time = Instant::now();
+                let mut prev_heading = imu.heading();
+                loop {
+                    let heading = imu.rotation();
+                    let dh = heading - prev_heading;
+                    prev_heading = heading;
+
+                    let (dx, dy) = local_displacement(&mut wheel_1, &mut wheel_2, dh);
+                    let dt = Time::new::<second>(prev_time.elapsed().as_secs_f64());
+                    prev_time = Instant::now();
+
+                    {
+                        let mut state = state.borrow_mut();
+                        state.predict(dx, dy, dh, &settings);
+                        if let Some((time, reading)) = otos.latest() {
+                            state.correct_with(time, reading, &settings);
+                        }
+
+                        state.pose.vf = dx / dt;
+                        state.pose.vs = dy / dt;
+                        state.pose.omega = (dh / dt).into();
+                        let pose = state.pose;
+                        state.history.push(Instant::now(), pose);
+                    }
+
+                    let wait = timer.borrow_mut().tick();
+                    sleep(wait).await;
+                }
+            }),
+        }
+    }
+
+    /// Variance of the x, y and heading estimates in meters and radians squared.
+    pub fn variance(&self) -> [f64; 3] {
+        let covariance = self.state.borrow().covariance;
+        [covariance[0][0], covariance[1][1], covariance[2][2]]
+    }
+
+    pub fn stats(&self) -> LoopStats {
+        self.timer.borrow().stats()
+    }
+
+    /// Logs the loop stats every `interval`, or stops logging them with `None`.
+    pub fn log_stats(&mut self, interval: Option<Duration>) {
+        self.timer.borrow_mut().log_every(interval);
+    }
+}
+
+impl Localizer for Ekf {
+    fn pose(&self) -> Pose {
+        self.state.borrow().pose
+    }
+
+    fn set_pose(&mut self, pose: Pose) {
+        let mut state = self.state.borrow_mut();
+        let correction = pose.compose(state.pose.inverse());
+        state.history.transform(correction);
+        state.pose = pose;
+        state.covariance = [[0.0; 3]; 3];
+        state.otos_offset = pose.compose(self.otos.pose().inverse());
+    }
+
+    fn pose_at(&self, time: Instant) -> Option<Pose> {
+        self.state.borrow().history.pose_at(time)
+    }
+}
+
+impl State {
+    fn predict(&mut self, dx: Length, dy: Length, dh: Angle, settings: &EkfSettings) {
+        let (dx, dy, dh) = (dx.get::<meter>(), dy.get::<meter>(), dh.get::<radian>());
+        let heading = self.pose.h.get::<radian>() + dh / 2.0;
+        let (sin, cos) = heading.sin_cos();
+
+        self.pose.x += Length::new::<meter>(cos * dx - sin * dy);
+        self.pose.y += Length::new::<meter>(sin * dx + cos * dy);
+        self.pose.h += Angle::new::<radian>(dh);
+
+        // how the new position depends on the old heading
+        let jacobian = [
+            [1.0, 0.0, -sin * dx - cos * dy],
+            [0.0, 1.0, cos * dx - sin * dy],
+            [0.0, 0.0, 1.0],
+        ];
+
+        let translation = settings.translation_noise * dx.hypot(dy);
+        let rotation = settings.rotation_noise * dh.abs();
+        let process = diagonal([
+            translation * translation,
+            translation * translation,
+            rotation * rotation,
+        ]);
+
+        self.covariance = add(
+            multiply(multiply(jacobian, self.covariance), transpose(jacobian)),
+            process,
+        );
+    }
+
+    // the OTOS updates slower than the filter and its readings arrive late, so
+    // each one is only used once, carried forward by how far the filter thinks
+    // the robot moved since it was taken
+    fn correct_with(&mut self, time: Instant, reading: Pose, settings: &EkfSettings) {
+        if self.last_reading.is_some_and(|last| time <= last) {
+            return;
+        }
+        self.last_reading = Some(time);
+
+        let measurement = self.otos_offset.compose(reading);
+        let then = self.history.pose_at(time).unwrap_or(self.pose);
+        let moved = then.inverse().compose(self.pose);
+        self.correct(measurement.compose(moved), settings);
+    }
+
+    fn correct(&mut self, measurement: Pose, settings: &EkfSettings) {
+        let innovation = [
+            (measurement.x - self.pose.x).get::<meter>(),
+            (measurement.y - self.pose.y).get::<meter>(),
+            wrapped((measurement.h - self.pose.h).get::<radian>()),
+        ];
+
+        let noise = diagonal([
+            settings.otos_position_noise.powi(2),
+            settings.otos_position_noise.powi(2),
+            settings.otos_heading_noise.powi(2),
+        ]);
+        let Some(inverse) = invert(add(self.covariance, noise)) else {
+            return;
+        };
+        let gain = multiply(self.covariance, inverse);
+
+        let correction =
+            gain.map(|row| row.iter().zip(innovation).map(|(k, e)| k * e).sum::<f64>());
+        self.pose.x += Length::new::<meter>(correction[0]);
+        self.pose.y += Length::new::<meter>(correction[1]);
+        self.pose.h += Angle::new::<radian>(correction[2]);
+
+        let mut identity_minus_gain = IDENTITY;
+        for (row, gain_row) in identity_minus_gain.iter_mut().zip(gain) {
+            for (value, k) in row.iter_mut().zip(gain_row) {
+                *value -= k;
+            }
+        }
+        self.covariance = multiply(identity_minus_gain, self.covariance);
+    }
+}
+
+fn diagonal(values: [f64; 3]) -> Matrix {
+    let mut matrix = [[0.0; 3]; 3];
+    for (i, value) in values.into_iter().enumerate() {
+        matrix[i][i] = value;
+    }
+
+    matrix
+}
+
+fn add(a: Matrix, b: Matrix) -> Matrix {
+    let mut sum = a;
+    for (row, b_row) in sum.iter_mut().zip(b) {
+        for (value, b_value) in row.iter_mut().zip(b_row) {
+            *value += b_value;
+        }
+    }
+
+    sum
+}
+
+fn multiply(a: Matrix, b: Matrix) -> Matrix {
+    let mut product = [[0.0; 3]; 3];
+    for (i, row) in product.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+
+    product
+}
+
+fn transpose(a: Matrix) -> Matrix {
+    let mut transposed = [[0.0; 3]; 3];
+    for (i, row) in a.iter().enumerate() {
+        for (j, value) in row.iter().enumerate() {
+            transposed[j][i] = *value;
+        }
+    }
+
+    transposed
+}
+
+fn invert(a: Matrix) -> Option<Matrix> {
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| a[r0][c0] * a[r1][c1] - a[r0][c1] * a[r1][c0];
+
+    let determinant = a[0][0] * cofactor(1, 2, 1, 2) - a[0][1] * cofactor(1, 2, 0, 2)
+        + a[0][2] * cofactor(1, 2, 0, 1);
+    if determinant.abs() < f64::EPSILON {
+        return None;
+    }
+
+    let adjugate = [
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+        ],
+    ];
+
+    Some(adjugate.map(|row| row.map(|value| value / determinant)))
+}
@@ -0,0 +1,100 @@
+use uom::si::{
+    angle::radian,
+    f64::{Angle, Length},
+    length::{inch, meter},
+};
+
+use super::vec2::Vec2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wall {
+    /// The wall at the smallest x.
+    Left,
+    /// The wall at the largest x.
+    Right,
+    /// The wall at the smallest y.
+    Bottom,
+    /// The wall at the largest y.
+    Top,
+}
+
+#[derive(Clone, Copy)]
+pub struct Hit {
+    pub wall: Wall,
+    pub distance: Length,
+    /// Angle between the ray and the wall's normal.
+    pub incidence: Angle,
+}
+
+/// The field perimeter as a rectangle of walls in the same frame as the
+/// robot's pose.
+#[derive(Clone, Copy)]
+pub struct Field {
+    min: Vec2<Length>,
+    max: Vec2<Length>,
+}
+
+impl Field {
+    pub fn new(min: Vec2<Length>, max: Vec2<Length>) -> Self {
+        Self { min, max }
+    }
+
+    /// A 12 foot field with the origin in a corner.
+    pub fn standard() -> Self {
+        Self::new(
+            Vec2::new(Length::new::<inch>(0.0), Length::new::<inch>(0.0)),
+            Vec2::new(Length::new::<inch>(144.0), Length::new::<inch>(144.0)),
+        )
+    }
+
+    pub fn min(&self) -> Vec2<Length> {
+        self.min
+    }
+
+    pub fn max(&self) -> Vec2<Length> {
+        self.max
+    }
+
+    pub fn contains(&self, point: Vec2<Length>) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+    }
+
+    /// The first wall hit from `origin` facing `angle`. `None` when the origin
+    /// is outside of the field.
+    pub fn raycast(&self, origin: Vec2<Length>, angle: Angle) -> Option<Hit> {
+        if !self.contains(origin) {
+            return None;
+        }
+
+        let (sin, cos) = angle.get::<radian>().sin_cos();
+        let (x, y) = (origin.x.get::<meter>(), origin.y.get::<meter>());
+
+        // distance to the wall in the direction of travel along each axis
+        let to_wall = |position: f64, direction: f64, min: Length, max: Length| {
+            if direction > f64::EPSILON {
+                (max.get::<meter>() - position) / direction
+            } else if direction < -f64::EPSILON {
+                (min.get::<meter>() - position) / direction
+            } else {
+                f64::INFINITY
+            }
+        };
+
+        let x_wall = to_wall(x, cos, self.min.x, self.max.x);
+        let y_wall = to_wall(y, sin, self.min.y, self.max.y);
+
+        let (wall, distance, incidence) = if x_wall < y_wall {
+            let wall = if cos > 0.0 { Wall::Right } else { Wall::Left };
+            (wall, x_wall, cos.abs().acos())
+        } else {
+            let wall = if sin > 0.0 { Wall::Top } else { Wall::Bottom };
+            (wall, y_wall, sin.abs().acos())
+        };
+
+        Some(Hit {
+            wall,
+            distance: Length::new::<meter>(distance),
+            incidence: Angle::new::<radian>(incidence),
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+
+use super::pose::Pose;
+use crate::runtime::Instant;
+
+/// The most recent poses along with when they were measured, for looking up
+/// where the robot was when a late sensor reading was taken.
+pub struct PoseHistory {
+    samples: VecDeque<(Instant, Pose)>,
+    capacity: usize,
+}
+
+impl PoseHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Samples must be pushed in time order. The oldest one is dropped once
+    /// the history is full.
+    pub fn push(&mut self, time: Instant, pose: Pose) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((time, pose));
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Moves every stored pose by `correction`, applied in the field frame, so
+    /// the history stays in the same frame as a pose that was just corrected.
+    pub fn transform(&mut self, correction: Pose) {
+        for (_, pose) in self.samples.iter_mut() {
+            *pose = correction.compose(*pose);
+        }
+    }
+
+    /// The most recent sample, `None` while the history is empty.
+    pub fn newest(&self) -> Option<(Instant, Pose)> {
+        self.samples.back().copied()
+    }
+
+    /// The pose at `time`, interpolated between the samples around it. Times
+    /// after the newest sample get the newest pose and times before the oldest
+    /// one get `None`.
+    pub fn pose_at(&self, time: Instant) -> Option<Pose> {
+        let &(newest_time, newest) = self.samples.back()?;
+        if time >= newest_time {
+            return Some(newest);
+        }
+
+        let next = self.samples.partition_point(|&(sample, _)| sample <= time);
+        if next == 0 {
+            return None;
+        }
+
+        let (from_time, from) = self.samples[next - 1];
+        let (to_time, to) = self.samples[next];
+        let t = (time - from_time).as_secs_f64() / (to_time - from_time).as_secs_f64();
+
+        Some(from.interpolate(to, t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uom::si::{
+        angle::degree,
+        f64::{Angle, Length},
+        length::inch,
+    };
+
+    use super::PoseHistory;
+    use crate::{localization::pose::Pose, runtime::Instant};
+
+    fn pose(x: f64, y: f64, h: f64) -> Pose {
+        Pose::new(
+            Length::new::<inch>(x),
+            Length::new::<inch>(y),
+            Angle::new::<degree>(h),
+        )
+    }
+
+    fn assert_pose(actual: Pose, expected: Pose) {
+        assert!(
+            (actual.x - expected.x).abs().get::<inch>() < 1e-9
+                && (actual.y - expected.y).abs().get::<inch>() < 1e-9
+                && (actual.h - expected.h).abs().get::<degree>() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn poses_between_samples_are_interpolated() {
+        let start = Instant::now();
+        let mut history = PoseHistory::new(4);
+        history.push(start, pose(0.0, 0.0, 0.0));
+        history.push(start + Duration::from_millis(10), pose(10.0, 20.0, 30.0));
+
+        assert_pose(
+            history.pose_at(start + Duration::from_millis(5)).unwrap(),
+            pose(5.0, 10.0, 15.0),
+        );
+        assert_pose(
+            history.pose_at(start + Duration::from_secs(1)).unwrap(),
+            pose(10.0, 20.0, 30.0),
+        );
+    }
+
+    #[test]
+    fn the_oldest_sample_is_dropped_when_full() {
+        let start = Instant::now();
+        let mut history = PoseHistory::new(2);
+        for i in 0..3 {
+            history.push(
+                start + Duration::from_millis(10 * i),
+                pose(i as f64, 0.0, 0.0),
+            );
+        }
+
+        assert!(history.pose_at(start).is_none());
+        assert_pose(
+            history.pose_at(start + Duration::from_millis(10)).unwrap(),
+            pose(1.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn transformed_samples_follow_the_correction() {
+        let start = Instant::now();
+        let mut history = PoseHistory::new(4);
+        history.push(start, pose(0.0, 0.0, 0.0));
+        history.push(start + Duration::from_millis(10), pose(10.0, 0.0, 0.0));
+
+        // the robot turns out to be at (10, 10) facing +y instead
+        let correction = pose(10.0, 10.0, 90.0).compose(pose(10.0, 0.0, 0.0).inverse());
+        history.transform(correction);
+
+        assert_pose(history.pose_at(start).unwrap(), pose(10.0, 0.0, 90.0));
+        assert_pose(
+            history.pose_at(start + Duration::from_millis(10)).unwrap(),
+            pose(10.0, 10.0, 90.0),
+        );
+    }
+}
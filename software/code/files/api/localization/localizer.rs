@@ -0,0 +1,57 @@
+use uom::si::f64::{AngularVelocity, Velocity};
+
+use super::pose::Pose;
+use crate::runtime::Instant;
+
+/// Anything that can track where the robot is on the field.
+pub trait Localizer {
+    fn pose(&self) -> Pose;
+
+    fn set_pose(&mut self, pose: Pose);
+
+    /// Where the robot was at `time`, if the localizer keeps a history that
+    /// goes back that far.
+    fn pose_at(&self, _time: Instant) -> Option<Pose> {
+        None
+    }
+
+    /// Velocity towards the front of the robot.
+    fn forward_velocity(&self) -> Velocity {
+        self.pose().vf
+    }
+
+    /// Velocity towards the left of the robot.
+    fn sideways_velocity(&self) -> Velocity {
+        self.pose().vs
+    }
+
+    fn angular_velocity(&self) -> AngularVelocity {
+        self.pose().omega
+    }
+}
+
+impl<L: Localizer + ?Sized> Localizer for Box<L> {
+    fn pose(&self) -> Pose {
+        (**self).pose()
+    }
+
+    fn set_pose(&mut self, pose: Pose) {
+        (**self).set_pose(pose);
+    }
+
+    fn pose_at(&self, time: Instant) -> Option<Pose> {
+        (**self).pose_at(time)
+    }
+
+    fn forward_velocity(&self) -> Velocity {
+        (**self).forward_velocity()
+    }
+
+    fn sideways_velocity(&self) -> Velocity {
+        (**self).sideways_velocity()
+    }
+
+    fn angular_velocity(&self) -> AngularVelocity {
+        (**self).angular_velocity()
+    }
+}
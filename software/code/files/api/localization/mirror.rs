@@ -0,0 +1,95 @@
+use std::{cell::RefCell, rc::Rc};
+
+use uom::si::f64::{Angle, Length};
+
+use super::{field::Field, pose::Pose, vec2::Vec2};
+use crate::subsystems::{Color, RobotSettings};
+
+/// The line routes are reflected across.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    /// The line of constant x through the field's center, for alliances on the
+    /// left and right of the field.
+    Vertical,
+    /// The line of constant y through the field's center, for alliances on the
+    /// bottom and top of the field.
+    Horizontal,
+}
+
+/// Reflects field positions and headings onto the other alliance's side so one
+/// route serves both. Does nothing when the robot is on the alliance the route
+/// was written for. The alliance is read from the settings each time, so
+/// picking a color on the selector after building the routes still counts.
+#[derive(Clone)]
+pub struct Mirror {
+    axis: Axis,
+    center: Length,
+    written_for: Color,
+    settings: Rc<RefCell<RobotSettings>>,
+}
+
+impl Mirror {
+    pub fn new(
+        field: &Field,
+        axis: Axis,
+        written_for: Color,
+        settings: Rc<RefCell<RobotSettings>>,
+    ) -> Self {
+        let center = match axis {
+            Axis::Vertical => (field.min().x + field.max().x) / 2.0,
+            Axis::Horizontal => (field.min().y + field.max().y) / 2.0,
+        };
+
+        Self {
+            axis,
+            center,
+            written_for,
+            settings,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.settings.borrow().color != self.written_for
+    }
+
+    pub fn point(&self, point: Vec2<Length>) -> Vec2<Length> {
+        if !self.is_active() {
+            return point;
+        }
+
+        match self.axis {
+            Axis::Vertical => Vec2::new(self.center * 2.0 - point.x, point.y),
+            Axis::Horizontal => Vec2::new(point.x, self.center * 2.0 - point.y),
+        }
+    }
+
+    pub fn heading(&self, heading: Angle) -> Angle {
+        if !self.is_active() {
+            return heading;
+        }
+
+        match self.axis {
+            Axis::Vertical => Angle::HALF_TURN - heading,
+            Axis::Horizontal => -heading,
+        }
+    }
+
+    /// Reflected turns go the other way, so swing radii and anything else that
+    /// is signed by turning direction flip.
+    pub fn turn<T: core::ops::Neg<Output = T>>(&self, value: T) -> T {
+        if self.is_active() { -value } else { value }
+    }
+
+    pub fn pose(&self, pose: Pose) -> Pose {
+        let position = self.point(pose.position());
+
+        Pose {
+            x: position.x,
+            y: position.y,
+            h: self.heading(pose.h),
+            vf: pose.vf,
+            vs: self.turn(pose.vs),
+            omega: self.turn(pose.omega),
+        }
+    }
+}
@@ -1,3 +1,11 @@
+pub mod ekf;
+pub mod field;
+pub mod history;
+pub mod localizer;
+pub mod mirror;
 pub mod odometry;
+pub mod particle_filter;
 pub mod pose;
+pub mod timing;
 pub mod vec2;
+pub mod wall_reset;
@@ -1,94 +1,483 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     rc::Rc,
-    time::{Duration, Instant},
+    time::Duration,
 };
 
-use uom::si::{f64::Time, time::second};
-use vexide::{
-    task::{Task, spawn},
-    time::sleep,
+use uom::si::{
+    angle::radian,
+    f64::{Angle, Length, Time},
+    length::meter,
+    time::second,
 };
 
-use super::pose::Pose;
-use crate::hardware::{imu::Imu, tracking_wheel::TrackingWheel};
+use super::{
+    history::PoseHistory,
+    localizer::Localizer,
+    pose::Pose,
+    timing::{LoopStats, LoopTimer},
+};
+use crate::{
+    hardware::{
+        device::{EncoderDevice, InertialDevice, MotorDevice},
+        imu::Imu,
+        motor_group::MotorGroup,
+        tracking_wheel::TrackingWheel,
+    },
+    runtime::{Instant, Task, sleep, spawn},
+    utils::gaussian_elimination,
+};
+
+/// How each measured motion is added onto the pose.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Integration {
+    /// Moves along the arc the motion traces out, exact for constant
+    /// velocities between updates.
+    #[default]
+    Exponential,
+    /// Moves in a straight line at the average heading, the original method
+    /// kept for comparison.
+    Midpoint,
+}
 
 pub struct Odometry {
     pose: Rc<RefCell<Pose>>,
-    _task: Task<()>,
+    history: Rc<RefCell<PoseHistory>>,
+    integration: Rc<Cell<Integration>>,
+    timer: Rc<RefCell<LoopTimer>>,
+    _task: Task,
 }
 
 impl Odometry {
-    pub fn new(
+    const HISTORY: Duration = Duration::from_secs(1);
+
+    /// Two tracking wheels at different angles with heading from the IMU.
+    pub fn new<E: EncoderDevice + 'static, I: InertialDevice + 'static>(
         starting_pose: Pose,
-        mut wheel_1: TrackingWheel,
-        mut wheel_2: TrackingWheel,
-        imu: Imu,
+        period: Duration,
+        mut wheel_1: TrackingWheel<E>,
+        mut wheel_2: TrackingWheel<E>,
+        imu: Imu<I>,
     ) -> Self {
-        let pose = Rc::new(RefCell::new(starting_pose));
-
-        Self {
-            pose: pose.clone(),
-            _task: spawn(async move {
-                let mut prev_time = Instant::now();
-                let mut prev_heading = imu.heading();
-                loop {
-                    let ds1 = wheel_1.traveled();
-                    let ds2 = wheel_2.traveled();
-
-                    let heading = imu.rotation();
-                    let dh = heading - prev_heading;
-                    prev_heading = heading;
+        let mut prev_heading = imu.heading();
+        Self::integrate(starting_pose, period, move || {
+            let heading = imu.rotation();
+            let dh = heading - prev_heading;
+            prev_heading = heading;
 
-                    let phi1 = wheel_1.angle();
-                    let phi2 = wheel_2.angle();
+            let (dx, dy) = local_displacement(&mut wheel_1, &mut wheel_2, dh);
+            (dx, dy, dh)
+        })
+    }
 
-                    let offset1 = wheel_1.from_center();
-                    let offset2 = wheel_2.from_center();
+    /// One perpendicular tracking wheel for sideways travel, the drive motors
+    /// for forward travel and heading from the IMU.
+    pub fn with_drive_encoders<
+        E: EncoderDevice + 'static,
+        M: MotorDevice + 'static,
+        I: InertialDevice + 'static,
+    >(
+        starting_pose: Pose,
+        period: Duration,
+        mut perpendicular: TrackingWheel<E>,
+        mut left: TrackingWheel<MotorGroup<M>>,
+        mut right: TrackingWheel<MotorGroup<M>>,
+        imu: Imu<I>,
+    ) -> Self {
+        let mut prev_heading = imu.heading();
+        Self::integrate(starting_pose, period, move || {
+            let heading = imu.rotation();
+            let dh = heading - prev_heading;
+            prev_heading = heading;
 
-                    let dx_rot1 = -dh * offset1.y;
-                    let dy_rot1 = dh * offset1.x;
-                    let dx_rot2 = -dh * offset2.y;
-                    let dy_rot2 = dh * offset2.x;
+            let readings = [
+                Reading::from(&mut perpendicular),
+                Reading::from(&mut left),
+                Reading::from(&mut right),
+            ];
+            displacement(&readings, Some(dh))
+        })
+    }
 
-                    // projection of that rotation along each wheel axis (Length)
-                    let ds1_rot = dx_rot1 * phi1.cos() + dy_rot1 * phi1.sin();
-                    let ds2_rot = dx_rot2 * phi2.cos() + dy_rot2 * phi2.sin();
+    /// Only the drive motors, with heading from the difference between the
+    /// sides. Sideways slip can't be seen and is taken to be zero.
+    pub fn from_drive_encoders<M: MotorDevice + 'static>(
+        starting_pose: Pose,
+        period: Duration,
+        mut left: TrackingWheel<MotorGroup<M>>,
+        mut right: TrackingWheel<MotorGroup<M>>,
+    ) -> Self {
+        Self::integrate(starting_pose, period, move || {
+            let readings = [Reading::from(&mut left), Reading::from(&mut right)];
+            displacement(&readings, None)
+        })
+    }
 
-                    // corrected wheel distances (translation only)
-                    let ds1_corr = ds1 - ds1_rot;
-                    let ds2_corr = ds2 - ds2_rot;
+    /// Three tracking wheels with heading from the difference between them
+    /// instead of an IMU. At least two have to be offset from each other along
+    /// the same direction to see turning.
+    pub fn three_wheel<E: EncoderDevice + 'static>(
+        starting_pose: Pose,
+        period: Duration,
+        mut wheel_1: TrackingWheel<E>,
+        mut wheel_2: TrackingWheel<E>,
+        mut wheel_3: TrackingWheel<E>,
+    ) -> Self {
+        Self::integrate(starting_pose, period, move || {
+            let readings = [
+                Reading::from(&mut wheel_1),
+                Reading::from(&mut wheel_2),
+                Reading::from(&mut wheel_3),
+            ];
+            displacement(&readings, None)
+        })
+    }
 
-                    let det = (phi2 - phi1).sin();
+    // every configuration only differs in how it measures the motion in the
+    // robot's frame, which this integrates into the field frame
+    fn integrate(
+        starting_pose: Pose,
+        period: Duration,
+        mut measure: impl FnMut() -> (Length, Length, Angle) + 'static,
+    ) -> Self {
+        let pose = Rc::new(RefCell::new(starting_pose));
+        let history = Rc::new(RefCell::new(PoseHistory::new(
+            (Self::HISTORY.as_millis() / period.as_millis().max(1)).max(1) as usize,
+        )));
+        let integration = Rc::new(Cell::new(Integration::default()));
+        let timer = Rc::new(RefCell::new(LoopTimer::new("Odometry", period)));
 
-                    let dx = (phi2.sin() * ds1_corr - phi1.sin() * ds2_corr) / det;
-                    let dy = (-phi2.cos() * ds1_corr + phi1.cos() * ds2_corr) / det;
+        Self {
+            pose: pose.clone(),
+            history: history.clone(),
+            integration: integration.clone(),
+            timer: timer.clone(),
+            _task: spawn(async move {
+                let mut prev_time = Instant::now();
+                loop {
+                    let (dx, dy, dh) = measure();
 
                     pose.replace_with(|prev| {
-                        let heading_avg = prev.h + dh / 2.0;
                         let dt = prev_time.elapsed().as_secs_f64();
                         Pose {
-                            // Doing vector rotation for odom and adding to position
-                            x: prev.x + (heading_avg.cos() * dx - heading_avg.sin() * dy),
-                            y: prev.y + (heading_avg.sin() * dx + heading_avg.cos() * dy),
-                            h: prev.h + dh,
                             vf: dx / Time::new::<second>(dt),
                             vs: dy / Time::new::<second>(dt),
                             omega: (dh / Time::new::<second>(dt)).into(),
+                            ..step(*prev, dx, dy, dh, integration.get())
                         }
                     });
+                    history.borrow_mut().push(Instant::now(), *pose.borrow());
                     prev_time = Instant::now();
-                    sleep(Duration::from_millis(10)).await;
+
+                    let wait = timer.borrow_mut().tick();
+                    sleep(wait).await;
                 }
             }),
         }
     }
 
-    pub fn get_pose(&self) -> Pose {
+    pub fn set_integration(&mut self, integration: Integration) {
+        self.integration.set(integration);
+    }
+
+    pub fn stats(&self) -> LoopStats {
+        self.timer.borrow().stats()
+    }
+
+    /// Logs the loop stats every `interval`, or stops logging them with `None`.
+    pub fn log_stats(&mut self, interval: Option<Duration>) {
+        self.timer.borrow_mut().log_every(interval);
+    }
+}
+
+impl Localizer for Odometry {
+    fn pose(&self) -> Pose {
         *self.pose.borrow() // Gets the position as a vector
     }
 
-    pub fn set_pose(&mut self, pose: Pose) {
-        *self.pose.borrow_mut() = pose; // Sets the position vector
+    fn set_pose(&mut self, pose: Pose) {
+        let prev = self.pose.replace(pose); // Sets the position vector
+        // moves the old poses by the same correction so late readings still
+        // line up, like `Otos` does with its offset
+        let correction = pose.compose(prev.inverse());
+        self.history.borrow_mut().transform(correction);
+    }
+
+    fn pose_at(&self, time: Instant) -> Option<Pose> {
+        self.history.borrow().pose_at(time)
+    }
+}
+
+/// Adds a motion measured in the robot's frame onto `prev`.
+pub(crate) fn step(
+    prev: Pose,
+    dx: Length,
+    dy: Length,
+    dh: Angle,
+    integration: Integration,
+) -> Pose {
+    let (dx, dy) = match integration {
+        Integration::Exponential => {
+            // SE(2) exponential map, bends the motion around the arc it was
+            // measured along
+            let theta = dh.get::<radian>();
+            let (s, c) = if theta.abs() < 1e-9 {
+                // limits as theta goes to zero
+                (1.0 - theta * theta / 6.0, theta / 2.0)
+            } else {
+                (theta.sin() / theta, (1.0 - theta.cos()) / theta)
+            };
+
+            (dx * s - dy * c, dx * c + dy * s)
+        }
+        Integration::Midpoint => {
+            let half = dh / 2.0;
+            (
+                half.cos() * dx - half.sin() * dy,
+                half.sin() * dx + half.cos() * dy,
+            )
+        }
+    };
+
+    // Doing vector rotation for odom and adding to position
+    Pose::new(
+        prev.x + (prev.h.cos() * dx - prev.h.sin() * dy),
+        prev.y + (prev.h.sin() * dx + prev.h.cos() * dy),
+        prev.h + dh,
+    )
+}
+
+/// Translation in the robot's frame measured by two tracking wheels, after
+/// removing the travel caused by the robot turning `dh`.
+pub(crate) fn local_displacement<E: EncoderDevice>(
+    wheel_1: &mut TrackingWheel<E>,
+    wheel_2: &mut TrackingWheel<E>,
+    dh: Angle,
+) -> (Length, Length) {
+    let readings = [Reading::from(wheel_1), Reading::from(wheel_2)];
+    let (dx, dy, _) = displacement(&readings, Some(dh));
+
+    (dx, dy)
+}
+
+/// How far a wheel rolled since the last reading and where it sits.
+struct Reading {
+    traveled: f64,
+    // how much each of dx, dy and dh rolls the wheel, from its angle and offset
+    coefficients: [f64; 3],
+}
+
+impl<E: EncoderDevice> From<&mut TrackingWheel<E>> for Reading {
+    fn from(wheel: &mut TrackingWheel<E>) -> Self {
+        let (sin, cos) = wheel.angle().get::<radian>().sin_cos();
+        let offset = wheel.from_center();
+        let (x, y) = (offset.x.get::<meter>(), offset.y.get::<meter>());
+
+        Self {
+            traveled: wheel.traveled().get::<meter>(),
+            // turning moves the wheel's spot by (-y, x) per radian
+            coefficients: [cos, sin, -y * cos + x * sin],
+        }
+    }
+}
+
+/// Least squares fit of the motion in the robot's frame to any number of wheel
+/// readings. The heading change is solved for unless it is given, and motion
+/// none of the wheels can see is left at zero.
+fn displacement(readings: &[Reading], dh: Option<Angle>) -> (Length, Length, Angle) {
+    let mut solution = [0.0, 0.0, dh.map_or(0.0, |dh| dh.get::<radian>())];
+
+    let unknowns: Vec<usize> = (0..if dh.is_some() { 2 } else { 3 })
+        .filter(|&i| {
+            readings
+                .iter()
+                .any(|reading| reading.coefficients[i].abs() > 1e-9)
+        })
+        .collect();
+    let n = unknowns.len();
+
+    // normal equations with the known heading moved to the right hand side
+    let mut system = vec![vec![0.0; n + 1]; n];
+    for reading in readings {
+        let known: f64 = (0..3)
+            .filter(|i| !unknowns.contains(i))
+            .map(|i| reading.coefficients[i] * solution[i])
+            .sum();
+
+        for (row, &i) in unknowns.iter().enumerate() {
+            for (column, &j) in unknowns.iter().enumerate() {
+                system[row][column] += reading.coefficients[i] * reading.coefficients[j];
+            }
+            system[row][n] += reading.coefficients[i] * (reading.traveled - known);
+        }
+    }
+
+    if let Some(values) = gaussian_elimination(system) {
+        for (&i, value) in unknowns.iter().zip(values) {
+            solution[i] = value;
+        }
+    }
+
+    (
+        Length::new::<meter>(solution[0]),
+        Length::new::<meter>(solution[1]),
+        Angle::new::<radian>(solution[2]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+    use uom::si::{
+        angle::radian,
+        f64::{Angle, Length},
+        length::meter,
+    };
+
+    use super::{Integration, step};
+    use crate::localization::pose::Pose;
+
+    const TOLERANCE: f64 = 1e-9;
+
+    fn drive(
+        start: Pose,
+        forward: f64,
+        sideways: f64,
+        turn: f64,
+        steps: usize,
+        integration: Integration,
+    ) -> Pose {
+        let n = steps as f64;
+        (0..steps).fold(start, |pose, _| {
+            step(
+                pose,
+                Length::new::<meter>(forward / n),
+                Length::new::<meter>(sideways / n),
+                Angle::new::<radian>(turn / n),
+                integration,
+            )
+        })
+    }
+
+    fn assert_pose(pose: Pose, x: f64, y: f64, h: f64, tolerance: f64) {
+        let (px, py, ph) = (
+            pose.x.get::<meter>(),
+            pose.y.get::<meter>(),
+            pose.h.get::<radian>(),
+        );
+        assert!(
+            (px - x).abs() < tolerance && (py - y).abs() < tolerance && (ph - h).abs() < tolerance,
+            "expected ({x}, {y}, {h}), got ({px}, {py}, {ph})"
+        );
+    }
+
+    fn origin() -> Pose {
+        Pose::new(Length::default(), Length::default(), Angle::default())
+    }
+
+    #[test]
+    fn straight_line() {
+        let start = Pose::new(
+            Length::new::<meter>(1.0),
+            Length::new::<meter>(2.0),
+            Angle::new::<radian>(FRAC_PI_2),
+        );
+        let pose = drive(start, 3.0, 0.0, 0.0, 1, Integration::Exponential);
+
+        assert_pose(pose, 1.0, 5.0, FRAC_PI_2, TOLERANCE);
+    }
+
+    #[test]
+    fn quarter_arc_in_one_step() {
+        // radius 2 turning left, the center is at (0, 2)
+        let radius = 2.0;
+        let pose = drive(
+            origin(),
+            radius * FRAC_PI_2,
+            0.0,
+            FRAC_PI_2,
+            1,
+            Integration::Exponential,
+        );
+
+        assert_pose(pose, radius, radius, FRAC_PI_2, TOLERANCE);
+    }
+
+    #[test]
+    fn right_arc_matches_closed_form() {
+        let (radius, angle): (f64, f64) = (1.5, -1.2);
+        let pose = drive(
+            origin(),
+            radius * angle.abs(),
+            0.0,
+            angle,
+            3,
+            Integration::Exponential,
+        );
+
+        // center is at (0, -radius)
+        let x = radius * angle.abs().sin();
+        let y = -radius * (1.0 - angle.cos());
+        assert_pose(pose, x, y, angle, TOLERANCE);
+    }
+
+    #[test]
+    fn strafing_arc_matches_closed_form() {
+        // sliding left while turning left swings the robot around a center
+        // behind it at (-radius, 0)
+        let (radius, angle): (f64, f64) = (0.8, 0.9);
+        let pose = drive(
+            origin(),
+            0.0,
+            radius * angle,
+            angle,
+            1,
+            Integration::Exponential,
+        );
+
+        let x = -radius * (1.0 - angle.cos());
+        let y = radius * angle.sin();
+        assert_pose(pose, x, y, angle, TOLERANCE);
+    }
+
+    #[test]
+    fn full_circle_returns_to_start() {
+        let radius = 1.0;
+        let pose = drive(
+            origin(),
+            radius * TAU,
+            0.0,
+            TAU,
+            7,
+            Integration::Exponential,
+        );
+
+        assert_pose(pose, 0.0, 0.0, TAU, TOLERANCE);
+    }
+
+    #[test]
+    fn midpoint_drifts_on_coarse_arcs() {
+        let radius = 1.0;
+        let exponential = drive(origin(), radius * PI, 0.0, PI, 4, Integration::Exponential);
+        let midpoint = drive(origin(), radius * PI, 0.0, PI, 4, Integration::Midpoint);
+
+        assert_pose(exponential, 0.0, 2.0 * radius, PI, TOLERANCE);
+        assert!((midpoint.y.get::<meter>() - 2.0 * radius).abs() > 1e-3);
+    }
+
+    #[test]
+    fn midpoint_converges_with_small_steps() {
+        let radius = 1.0;
+        let pose = drive(
+            origin(),
+            radius * PI,
+            0.0,
+            PI,
+            10_000,
+            Integration::Midpoint,
+        );
+
+        assert_pose(pose, 0.0, 2.0 * radius, PI, 1e-6);
     }
 }
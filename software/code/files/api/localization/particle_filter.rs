@@ -0,0 +1,353 @@
+use std::{cell::RefCell, f64::consts::TAU, rc::Rc, time::Duration};
+
+use uom::si::{
+    angle::radian,
+    f64::{Angle, Length},
+    length::meter,
+};
+
+use super::{
+    field::Field,
+    localizer::Localizer,
+    odometry::Odometry,
+    pose::Pose,
+    timing::{LoopStats, LoopTimer},
+    vec2::Vec2,
+};
+use crate::{
+    hardware::{device::DistanceDevice, rangefinder::Rangefinder},
+    runtime::{Task, sleep, spawn},
+    utils::wrapped,
+};
+
+/// Noise of each source as standard deviations, in meters and radians.
+#[derive(Clone, Copy)]
+pub struct ParticleFilterSettings {
+    pub particles: usize,
+    /// Drift of the odometry per meter traveled.
+    pub translation_noise: f64,
+    /// Drift of the odometry per radian turned.
+    pub rotation_noise: f64,
+    pub sensor_noise: f64,
+    /// How far the particles are scattered around a pose when it is set.
+    pub position_spread: f64,
+    pub heading_spread: f64,
+}
+
+impl Default for ParticleFilterSettings {
+    fn default() -> Self {
+        Self {
+            particles: 300,
+            translation_noise: 0.05,
+            rotation_noise: 0.02,
+            sensor_noise: 0.03,
+            position_spread: 0.05,
+            heading_spread: 0.05,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Particle {
+    x: f64,
+    y: f64,
+    h: f64,
+    weight: f64,
+}
+
+struct State {
+    pose: Pose,
+    particles: Vec<Particle>,
+    random: Random,
+    reset: bool,
+}
+
+/// Monte Carlo localization that moves a cloud of guesses by the odometry and
+/// weighs each one by how well the distance sensors agree with the field walls
+/// from there.
+pub struct ParticleFilter {
+    state: Rc<RefCell<State>>,
+    odometry: Rc<RefCell<Odometry>>,
+    settings: ParticleFilterSettings,
+    timer: Rc<RefCell<LoopTimer>>,
+    _task: Task,
+}
+
+impl ParticleFilter {
+    // readings that disagree with every wall (another robot, a game element)
+    // still leave a particle some weight instead of wiping it out
+    const OUTLIER_LIKELIHOOD: f64 = 0.05;
+
+    pub fn new<D: DistanceDevice + 'static>(
+        odometry: Odometry,
+        period: Duration,
+        sensors: Vec<Rangefinder<D>>,
+        field: Field,
+        settings: ParticleFilterSettings,
+    ) -> Self {
+        let starting_pose = odometry.pose();
+        let odometry = Rc::new(RefCell::new(odometry));
+
+        let mut random = Random::new(0x2545_f491_4f6c_dd1d);
+        let particles = scatter(starting_pose, &settings, &mut random);
+        let state = Rc::new(RefCell::new(State {
+            pose: starting_pose,
+            particles,
+            random,
+            reset: false,
+        }));
+        let timer = Rc::new(RefCell::new(LoopTimer::new("Particle filter", period)));
+
+        Self {
+            state: state.clone(),
+            odometry: odometry.clone(),
+            settings,
+            timer: timer.clone(),
+            _task: spawn(async move {
+                let mut prev = odometry.borrow().pose();
+                loop {
+                    let current = odometry.borrow().pose();
+                    {
+                        let mut state = state.borrow_mut();
+
+                        if state.reset {
+                            // the jump to the new pose isn't motion
+                            state.reset = false;
+                        } else {
+                            let (dx, dy, dh) = delta(prev, current);
+                            let moved = dx.hypot(dy) > f64::EPSILON || dh.abs() > f64::EPSILON;
+
+                            if moved {
+                                state.predict(dx, dy, dh, &settings);
+
+                                // readings are only folded in after moving so that
+                                // sitting still doesn't make the filter overconfident
+                                let readings: Vec<(&Rangefinder<D>, f64)> = sensors
+                                    .iter()
+                                    .filter_map(|sensor| {
+                                        sensor.distance().map(|d| (sensor, d.get::<meter>()))
+                                    })
+                                    .collect();
+                                if !readings.is_empty() {
+                                    state.correct(&readings, &field, &settings);
+                                }
+                            }
+                        }
+
+                        state.pose = Pose {
+                            vf: current.vf,
+                            vs: current.vs,
+                            omega: current.omega,
+                            ..state.estimate()
+                        };
+                    }
+                    prev = current;
+
+                    let wait = timer.borrow_mut().tick();
+                    sleep(wait).await;
+                }
+            }),
+        }
+    }
+
+    pub fn stats(&self) -> LoopStats {
+        self.timer.borrow().stats()
+    }
+
+    /// Logs the loop stats every `interval`, or stops logging them with `None`.
+    pub fn log_stats(&mut self, interval: Option<Duration>) {
+        self.timer.borrow_mut().log_every(interval);
+    }
+}
+
+impl Localizer for ParticleFilter {
+    fn pose(&self) -> Pose {
+        self.state.borrow().pose
+    }
+
+    /// Moves the robot to `pose` and scatters the particles around it.
+    fn set_pose(&mut self, pose: Pose) {
+        let mut state = self.state.borrow_mut();
+        state.particles = scatter(pose, &self.settings, &mut state.random);
+        state.pose = pose;
+        state.reset = true;
+        self.odometry.borrow_mut().set_pose(pose);
+    }
+}
+
+impl State {
+    fn predict(&mut self, dx: f64, dy: f64, dh: f64, settings: &ParticleFilterSettings) {
+        let translation = settings.translation_noise * dx.hypot(dy);
+        let rotation = settings.rotation_noise * dh.abs();
+
+        for particle in self.particles.iter_mut() {
+            let dx = dx + self.random.gaussian(translation);
+            let dy = dy + self.random.gaussian(translation);
+            let dh = dh + self.random.gaussian(rotation);
+
+            let (sin, cos) = (particle.h + dh / 2.0).sin_cos();
+            particle.x += cos * dx - sin * dy;
+            particle.y += sin * dx + cos * dy;
+            particle.h += dh;
+        }
+    }
+
+    fn correct<D: DistanceDevice>(
+        &mut self,
+        readings: &[(&Rangefinder<D>, f64)],
+        field: &Field,
+        settings: &ParticleFilterSettings,
+    ) {
+        let variance = settings.sensor_noise * settings.sensor_noise;
+
+        for particle in self.particles.iter_mut() {
+            let pose = particle.pose();
+
+            for (sensor, measured) in readings {
+                let (origin, angle) = sensor.ray(pose);
+                particle.weight *= match field.raycast(origin, angle) {
+                    Some(hit) => {
+                        let error = measured - hit.distance.get::<meter>();
+                        (-error * error / (2.0 * variance)).exp()
+                            + ParticleFilter::OUTLIER_LIKELIHOOD
+                    }
+                    // outside of the field
+                    None => 0.0,
+                };
+            }
+        }
+
+        let total: f64 = self.particles.iter().map(|particle| particle.weight).sum();
+        let count = self.particles.len() as f64;
+        if total <= 0.0 {
+            // nothing agrees with the sensors, so start trusting them all again
+            for particle in self.particles.iter_mut() {
+                particle.weight = 1.0 / count;
+            }
+            return;
+        }
+
+        for particle in self.particles.iter_mut() {
+            particle.weight /= total;
+        }
+
+        let effective: f64 = 1.0
+            / self
+                .particles
+                .iter()
+                .map(|particle| particle.weight * particle.weight)
+                .sum::<f64>();
+        if effective < count / 2.0 {
+            self.resample();
+        }
+    }
+
+    // low variance resampling, walks the cumulative weights in even steps from
+    // a single random offset
+    fn resample(&mut self) {
+        let count = self.particles.len();
+        let step = 1.0 / count as f64;
+        let mut target = self.random.uniform() * step;
+        let mut cumulative = self.particles[0].weight;
+        let mut index = 0;
+
+        let mut resampled = Vec::with_capacity(count);
+        for _ in 0..count {
+            while target > cumulative && index < count - 1 {
+                index += 1;
+                cumulative += self.particles[index].weight;
+            }
+
+            resampled.push(Particle {
+                weight: step,
+                ..self.particles[index]
+            });
+            target += step;
+        }
+
+        self.particles = resampled;
+    }
+
+    fn estimate(&self) -> Pose {
+        let (mut x, mut y, mut sin, mut cos) = (0.0, 0.0, 0.0, 0.0);
+        for particle in self.particles.iter() {
+            x += particle.weight * particle.x;
+            y += particle.weight * particle.y;
+            sin += particle.weight * particle.h.sin();
+            cos += particle.weight * particle.h.cos();
+        }
+
+        Pose::new(
+            Length::new::<meter>(x),
+            Length::new::<meter>(y),
+            Angle::new::<radian>(sin.atan2(cos)),
+        )
+    }
+}
+
+impl Particle {
+    fn pose(&self) -> Pose {
+        Pose::new(
+            Length::new::<meter>(self.x),
+            Length::new::<meter>(self.y),
+            Angle::new::<radian>(self.h),
+        )
+    }
+}
+
+// motion between two odometry poses in the frame of the first
+fn delta(from: Pose, to: Pose) -> (f64, f64, f64) {
+    let translation = Vec2::new(
+        (to.x - from.x).get::<meter>(),
+        (to.y - from.y).get::<meter>(),
+    )
+    .rotated(-from.h.get::<radian>());
+
+    (
+        translation.x,
+        translation.y,
+        wrapped((to.h - from.h).get::<radian>()),
+    )
+}
+
+fn scatter(pose: Pose, settings: &ParticleFilterSettings, random: &mut Random) -> Vec<Particle> {
+    let count = settings.particles.max(1);
+
+    (0..count)
+        .map(|_| Particle {
+            x: pose.x.get::<meter>() + random.gaussian(settings.position_spread),
+            y: pose.y.get::<meter>() + random.gaussian(settings.position_spread),
+            h: pose.h.get::<radian>() + random.gaussian(settings.heading_spread),
+            weight: 1.0 / count as f64,
+        })
+        .collect()
+}
+
+// xorshift generator, plenty for scattering particles without pulling in a
+// dependency
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn uniform(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn gaussian(&mut self, deviation: f64) -> f64 {
+        if deviation <= 0.0 {
+            return 0.0;
+        }
+
+        // Box-Muller, the first sample is kept off of zero for the log
+        let u = 1.0 - self.uniform();
+        let v = self.uniform();
+        deviation * (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+    }
+}
@@ -11,6 +11,9 @@ use uom::{
     },
 };
 
+use super::vec2::Vec2;
+use crate::utils::wrap;
+
 #[derive(Clone, Copy, Default)]
 pub struct Pose {
     pub x: Length,
@@ -32,6 +35,59 @@ impl Pose {
             omega: AngularVelocity::ZERO,
         }
     }
+
+    pub fn position(&self) -> Vec2<Length> {
+        Vec2::new(self.x, self.y)
+    }
+
+    /// Applies `other` as a motion in this pose's frame, so a pose measured
+    /// relative to this one ends up in the frame this one is in. The velocities
+    /// are in the robot's frame and come along from `other`.
+    pub fn compose(&self, other: Pose) -> Pose {
+        let position = self.position() + other.position().rotated_by(self.h);
+
+        Pose {
+            x: position.x,
+            y: position.y,
+            h: self.h + other.h,
+            ..other
+        }
+    }
+
+    /// The pose that undoes this one when composed with it.
+    pub fn inverse(&self) -> Pose {
+        let position = self.position().rotated_by(-self.h);
+
+        Pose::new(-position.x, -position.y, -self.h)
+    }
+
+    /// This pose as seen from `origin`.
+    pub fn relative_to(&self, origin: Pose) -> Pose {
+        origin.inverse().compose(*self)
+    }
+
+    /// Moves a point from this pose's frame into the field frame.
+    pub fn to_field(&self, point: Vec2<Length>) -> Vec2<Length> {
+        self.position() + point.rotated_by(self.h)
+    }
+
+    /// Moves a point from the field frame into this pose's frame.
+    pub fn to_robot(&self, point: Vec2<Length>) -> Vec2<Length> {
+        (point - self.position()).rotated_by(-self.h)
+    }
+
+    /// Blends linearly towards `other` as `t` goes from 0 to 1, turning the
+    /// short way around.
+    pub fn interpolate(&self, other: Pose, t: f64) -> Pose {
+        Pose {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+            h: self.h + wrap(other.h - self.h) * t,
+            vf: self.vf + (other.vf - self.vf) * t,
+            vs: self.vs + (other.vs - self.vs) * t,
+            omega: self.omega + (other.omega - self.omega) * t,
+        }
+    }
 }
 
 impl Display for Pose {
@@ -0,0 +1,119 @@
+use std::{fmt::Display, time::Duration};
+
+use log::info;
+
+use crate::runtime::Instant;
+
+/// How closely an update loop keeps to its period.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoopStats {
+    pub updates: u64,
+    pub min: Duration,
+    pub max: Duration,
+    /// Updates that came more than half a period late.
+    pub missed: u64,
+    total: Duration,
+}
+
+impl LoopStats {
+    pub fn mean(&self) -> Duration {
+        if self.updates == 0 {
+            return Duration::ZERO;
+        }
+
+        self.total.div_f64(self.updates as f64)
+    }
+
+    fn record(&mut self, interval: Duration, period: Duration) {
+        if self.updates == 0 {
+            self.min = interval;
+            self.max = interval;
+        } else {
+            self.min = self.min.min(interval);
+            self.max = self.max.max(interval);
+        }
+
+        self.updates += 1;
+        self.total += interval;
+
+        if interval > period + period / 2 {
+            self.missed += 1;
+        }
+    }
+}
+
+impl Display for LoopStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "min: {}us, max: {}us, mean: {}us, missed: {}/{}",
+            self.min.as_micros(),
+            self.max.as_micros(),
+            self.mean().as_micros(),
+            self.missed,
+            self.updates
+        )
+    }
+}
+
+/// Keeps a loop on a fixed period by sleeping until the next deadline instead
+/// of a fixed amount, and records how long each iteration actually took.
+pub(crate) struct LoopTimer {
+    name: &'static str,
+    period: Duration,
+    stats: LoopStats,
+    log_interval: Option<Duration>,
+    deadline: Instant,
+    prev: Option<Instant>,
+    last_log: Instant,
+}
+
+impl LoopTimer {
+    pub(crate) fn new(name: &'static str, period: Duration) -> Self {
+        let now = Instant::now();
+
+        Self {
+            name,
+            period,
+            stats: LoopStats::default(),
+            log_interval: None,
+            deadline: now,
+            prev: None,
+            last_log: now,
+        }
+    }
+
+    pub(crate) fn stats(&self) -> LoopStats {
+        self.stats
+    }
+
+    pub(crate) fn log_every(&mut self, interval: Option<Duration>) {
+        self.log_interval = interval;
+    }
+
+    /// Call once per iteration, returns how long to sleep.
+    pub(crate) fn tick(&mut self) -> Duration {
+        let now = Instant::now();
+
+        if let Some(prev) = self.prev {
+            self.stats.record(now - prev, self.period);
+        }
+        self.prev = Some(now);
+
+        if self
+            .log_interval
+            .is_some_and(|interval| now - self.last_log >= interval)
+        {
+            info!("{} loop: {}", self.name, self.stats);
+            self.last_log = now;
+        }
+
+        self.deadline += self.period;
+        // after falling behind, start over instead of rushing to catch up
+        if self.deadline < now {
+            self.deadline = now + self.period;
+        }
+
+        self.deadline - now
+    }
+}
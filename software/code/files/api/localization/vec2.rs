@@ -1,6 +1,10 @@
 use std::ops::{Add, Mul, Sub};
 
 use num_traits::Float;
+use uom::si::{
+    angle::radian,
+    f64::{Angle, Length},
+};
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Vec2<T> {
@@ -72,7 +76,7 @@ impl<T: Float + Copy + Mul<Output = T> + Sub<Output = T>> Vec2<T> {
 
 impl<T: Copy + Mul<Output = T> + Add<Output = T>> Vec2<T> {
     pub fn dot(&self, other: Vec2<T>) -> T {
-        self.x * other.x + self.y + other.y
+        self.x * other.x + self.y * other.y
     }
 }
 
@@ -89,6 +93,37 @@ impl<T: Float + Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T>> Vec2
     }
 }
 
+// Same operations as above without dropping the units, named apart since uom
+// could one day make lengths `Float` too.
+impl Vec2<Length> {
+    pub fn norm(&self) -> Length {
+        self.x.hypot(self.y)
+    }
+
+    pub fn distance_to(&self, other: Self) -> Length {
+        (*self - other).norm()
+    }
+
+    /// Angle of the vector counterclockwise from the x axis.
+    pub fn direction(&self) -> Angle {
+        self.y.atan2(self.x)
+    }
+
+    /// Direction from this point to `other`.
+    pub fn angle_to(&self, other: Self) -> Angle {
+        (other - *self).direction()
+    }
+
+    pub fn rotated_by(&self, angle: Angle) -> Self {
+        let (sin, cos) = angle.get::<radian>().sin_cos();
+
+        Self {
+            x: self.x * cos - self.y * sin,
+            y: self.x * sin + self.y * cos,
+        }
+    }
+}
+
 impl<T> From<(T, T)> for Vec2<T> {
     fn from(tuple: (T, T)) -> Self {
         Self {
@@ -0,0 +1,170 @@
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use log::{info, warn};
+use uom::si::{
+    angle::radian,
+    f64::{Angle, Length},
+    length::meter,
+};
+
+use super::{
+    field::{Field, Hit, Wall},
+    localizer::Localizer,
+    pose::Pose,
+};
+use crate::{
+    hardware::{
+        device::{DistanceDevice, MotorDevice},
+        rangefinder::Rangefinder,
+    },
+    subsystems::drivetrain::Drivetrain,
+    utils::wrapped,
+};
+
+/// Corrects the drivetrain's pose from distance sensors facing the field
+/// walls. The current pose is trusted enough to know which wall each sensor is
+/// looking at, so it can be called mid-route whenever a sensor has a clear view.
+pub struct WallReset {
+    field: Field,
+    max_incidence: Angle,
+}
+
+impl WallReset {
+    /// Readings that hit a wall more than `max_incidence` away from straight on
+    /// are rejected, since the sensor's beam spreads along the wall.
+    pub fn new(field: Field, max_incidence: Angle) -> Self {
+        Self {
+            field,
+            max_incidence,
+        }
+    }
+
+    /// Recomputes X or Y, whichever the wall in front of `sensor` measures.
+    /// Returns the corrected pose, or `None` if the reading was rejected and
+    /// the pose was left alone.
+    pub fn reset<M: MotorDevice, L: Localizer, D: DistanceDevice>(
+        &self,
+        dt: &mut Drivetrain<M, L>,
+        sensor: &Rangefinder<D>,
+    ) -> Option<Pose> {
+        let pose = dt.pose();
+        let (hit, measured) = self.reading(pose, sensor)?;
+        let corrected = shift(pose, sensor, hit, measured);
+
+        info!("Wall reset from ({}) to ({})", pose, corrected);
+        dt.set_pose(corrected);
+        Some(corrected)
+    }
+
+    /// Recomputes the heading from two sensors looking at the same wall, then
+    /// the X or Y that wall measures.
+    pub fn reset_with_heading<M: MotorDevice, L: Localizer, D: DistanceDevice>(
+        &self,
+        dt: &mut Drivetrain<M, L>,
+        first: &Rangefinder<D>,
+        second: &Rangefinder<D>,
+    ) -> Option<Pose> {
+        let pose = dt.pose();
+        let (first_hit, first_measured) = self.reading(pose, first)?;
+        let (second_hit, second_measured) = self.reading(pose, second)?;
+
+        if first_hit.wall != second_hit.wall {
+            warn!("Wall reset rejected: sensors face different walls");
+            return None;
+        }
+
+        // where each beam lands in the robot's frame, the line between them is
+        // the wall
+        let landing = |sensor: &Rangefinder<D>, measured: Length| {
+            let (sin, cos) = sensor.angle().get::<radian>().sin_cos();
+            let offset = sensor.from_center();
+            (
+                (offset.x + measured * cos).get::<meter>(),
+                (offset.y + measured * sin).get::<meter>(),
+            )
+        };
+        let (x1, y1) = landing(first, first_measured);
+        let (x2, y2) = landing(second, second_measured);
+        if (x2 - x1).hypot(y2 - y1) < 0.01 {
+            warn!("Wall reset rejected: sensors land on the same spot");
+            return None;
+        }
+
+        let wall_direction = match first_hit.wall {
+            Wall::Left | Wall::Right => FRAC_PI_2,
+            Wall::Bottom | Wall::Top => 0.0,
+        };
+        let measured_direction = (y2 - y1).atan2(x2 - x1);
+
+        // the wall's direction only fixes the heading up to a half turn, so the
+        // one closest to the current heading wins
+        let mut dh = wrapped(wall_direction - measured_direction - pose.h.get::<radian>());
+        dh -= PI * (dh / PI).round();
+        let rotated = Pose {
+            h: pose.h + Angle::new::<radian>(dh),
+            ..pose
+        };
+
+        let (first_hit, first_measured) = self.reading(rotated, first)?;
+        let (second_hit, second_measured) = self.reading(rotated, second)?;
+        let first_shift = shift(rotated, first, first_hit, first_measured);
+        let second_shift = shift(rotated, second, second_hit, second_measured);
+        let corrected = Pose {
+            x: (first_shift.x + second_shift.x) / 2.0,
+            y: (first_shift.y + second_shift.y) / 2.0,
+            ..rotated
+        };
+
+        info!("Wall reset from ({}) to ({})", pose, corrected);
+        dt.set_pose(corrected);
+        Some(corrected)
+    }
+
+    // the wall the sensor should see from `pose` and what it actually measured
+    fn reading<D: DistanceDevice>(
+        &self,
+        pose: Pose,
+        sensor: &Rangefinder<D>,
+    ) -> Option<(Hit, Length)> {
+        let (origin, angle) = sensor.ray(pose);
+        let Some(hit) = self.field.raycast(origin, angle) else {
+            warn!("Wall reset rejected: sensor is outside of the field");
+            return None;
+        };
+
+        if hit.incidence > self.max_incidence {
+            warn!("Wall reset rejected: sensor is too steep to the wall");
+            return None;
+        }
+
+        let Some(measured) = sensor.distance() else {
+            warn!("Wall reset rejected: nothing in range");
+            return None;
+        };
+
+        Some((hit, measured))
+    }
+}
+
+// moves the pose along the wall's normal until the expected distance matches
+// the measured one
+fn shift<D: DistanceDevice>(
+    pose: Pose,
+    sensor: &Rangefinder<D>,
+    hit: Hit,
+    measured: Length,
+) -> Pose {
+    let (_, angle) = sensor.ray(pose);
+    let error = hit.distance - measured;
+
+    match hit.wall {
+        Wall::Left | Wall::Right => Pose {
+            x: pose.x + error * angle.get::<radian>().cos(),
+            ..pose
+        },
+        Wall::Bottom | Wall::Top => Pose {
+            y: pose.y + error * angle.get::<radian>().sin(),
+            ..pose
+        },
+    }
+}
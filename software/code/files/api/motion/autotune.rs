@@ -0,0 +1,214 @@
+use std::{f64::consts::PI, time::Duration};
+
+use log::{info, warn};
+use uom::si::{angle::radian, length::meter};
+use vexide::prelude::Motor;
+
+use crate::{
+    controllers::pid::{AntiWindup, Pid},
+    hardware::device::MotorDevice,
+    localization::{localizer::Localizer, pose::Pose},
+    runtime::{Instant, sleep},
+    subsystems::drivetrain::Drivetrain,
+    utils::wrap,
+};
+
+/// How the measured oscillation is turned into gains.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TuningRule {
+    /// Fast and aggressive, expect some overshoot.
+    ZieglerNichols,
+    /// Slower with much less overshoot, usually the better start for a
+    /// drivetrain.
+    TyreusLuyben,
+}
+
+/// The relay experiment's results. The ultimate gain is in volts per radian
+/// for turns and volts per meter for drives.
+#[derive(Clone, Copy)]
+pub struct Oscillation {
+    pub ultimate_gain: f64,
+    pub period: Duration,
+}
+
+/// Finds PID gains with an Åström–Hägglund relay experiment. The drivetrain is
+/// driven with full relay voltage toward wherever it started, flipping each
+/// time it crosses, which settles into an oscillation whose period and size
+/// give the gains.
+pub struct AutoTune {
+    relay: f64,
+    hysteresis: f64,
+    cycles: usize,
+    rule: TuningRule,
+    timeout: Duration,
+}
+
+impl AutoTune {
+    const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// `relay` is in volts. `cycles` oscillations are averaged after the first
+    /// one, which is thrown out while the robot gets moving.
+    pub fn new(relay: f64, cycles: usize) -> Self {
+        Self {
+            relay,
+            hysteresis: 0.0,
+            cycles: cycles.max(1),
+            rule: TuningRule::TyreusLuyben,
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Error the relay ignores before flipping, in radians for turns and
+    /// meters for drives. Keeps sensor noise from flipping it early.
+    pub fn hysteresis(&mut self, hysteresis: f64) -> &mut Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    pub fn rule(&mut self, rule: TuningRule) -> &mut Self {
+        self.rule = rule;
+        self
+    }
+
+    pub fn timeout(&mut self, duration: Duration) -> &mut Self {
+        self.timeout = duration;
+        self
+    }
+
+    /// Tunes turning by oscillating the heading. The gains are in volts per
+    /// radian, ready for `Turn`.
+    pub async fn tune_turn<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+    ) -> Option<Pid> {
+        let mut prev_heading = dt.pose().h;
+        let mut turned = 0.0;
+
+        let oscillation = self
+            .relay(
+                dt,
+                |pose| {
+                    // unwrapped so crossing ±180° doesn't look like a jump
+                    turned += wrap(pose.h - prev_heading).get::<radian>();
+                    prev_heading = pose.h;
+                    turned
+                },
+                |dt, output| dt.set_voltages(-output, output),
+            )
+            .await?;
+
+        Some(self.gains(oscillation))
+    }
+
+    /// Tunes driving by oscillating along the starting heading. The gains are
+    /// in volts per meter, ready for `Linear`.
+    pub async fn tune_drive<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+    ) -> Option<Pid> {
+        let start = dt.pose();
+
+        let oscillation = self
+            .relay(
+                dt,
+                |pose| pose.relative_to(start).x.get::<meter>(),
+                |dt, output| dt.set_voltages(output, output),
+            )
+            .await?;
+
+        Some(self.gains(oscillation))
+    }
+
+    /// Gains from a measured oscillation using the chosen rule.
+    pub fn gains(&self, oscillation: Oscillation) -> Pid {
+        let ku = oscillation.ultimate_gain;
+        let tu = oscillation.period.as_secs_f64();
+
+        let (kp, ti, td) = match self.rule {
+            TuningRule::ZieglerNichols => (0.6 * ku, tu / 2.0, tu / 8.0),
+            TuningRule::TyreusLuyben => (ku / 2.2, 2.2 * tu, tu / 6.3),
+        };
+        let (ki, kd) = (kp / ti, kp * td);
+
+        info!("Tuned gains: kp {:.4}, ki {:.4}, kd {:.4}", kp, ki, kd);
+        Pid::new(kp, ki, kd, f64::INFINITY)
+            .output_limit(Motor::V5_MAX_VOLTAGE)
+            .anti_windup(AntiWindup::Conditional)
+    }
+
+    // runs the relay around a measurement that starts at zero and returns the
+    // averaged oscillation, or `None` if it didn't settle into one in time
+    async fn relay<M: MotorDevice, L: Localizer>(
+        &self,
+        dt: &mut Drivetrain<M, L>,
+        mut measure: impl FnMut(Pose) -> f64,
+        apply: impl Fn(&mut Drivetrain<M, L>, f64),
+    ) -> Option<Oscillation> {
+        let start = Instant::now();
+        let mut output = self.relay;
+
+        // time of the last flip to positive and the extremes since then
+        let mut cycle_start: Option<Duration> = None;
+        let (mut max, mut min) = (f64::MIN, f64::MAX);
+        let mut periods = Vec::new();
+        let mut amplitudes = Vec::new();
+
+        loop {
+            sleep(Duration::from_millis(10)).await;
+            let time = start.elapsed();
+            let measurement = measure(dt.pose());
+            max = max.max(measurement);
+            min = min.min(measurement);
+
+            if measurement < -self.hysteresis && output < 0.0 {
+                // a full cycle ends each time the relay flips back to positive
+                if let Some(cycle_start) = cycle_start {
+                    periods.push(time - cycle_start);
+                    amplitudes.push((max - min) / 2.0);
+                }
+                cycle_start = Some(time);
+                (max, min) = (measurement, measurement);
+                output = self.relay;
+            } else if measurement > self.hysteresis && output > 0.0 {
+                output = -self.relay;
+            }
+
+            if periods.len() > self.cycles {
+                break;
+            }
+
+            if time > self.timeout {
+                warn!("Auto-tune timed out after {} cycles", periods.len());
+                dt.set_voltages(0.0, 0.0);
+                return None;
+            }
+
+            apply(dt, output);
+        }
+
+        dt.set_voltages(0.0, 0.0);
+
+        // the first cycle is still getting up to speed
+        let count = self.cycles as f64;
+        let period = periods[1..].iter().sum::<Duration>() / self.cycles as u32;
+        let amplitude = amplitudes[1..].iter().sum::<f64>() / count;
+        if amplitude <= self.hysteresis {
+            warn!("Auto-tune oscillation is inside of the hysteresis");
+            return None;
+        }
+
+        let ultimate_gain =
+            4.0 * self.relay / (PI * (amplitude * amplitude - self.hysteresis.powi(2)).sqrt());
+        info!(
+            "Relay oscillation: amplitude {:.4}, period {}ms, ultimate gain {:.4}",
+            amplitude,
+            period.as_millis(),
+            ultimate_gain
+        );
+
+        Some(Oscillation {
+            ultimate_gain,
+            period,
+        })
+    }
+}
@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use log::{info, warn};
+use vexide::{io, prelude::Motor};
+
+use crate::{
+    controllers::{
+        FeedbackController,
+        feedforward::{Feedforward, FeedforwardSample},
+    },
+    hardware::{device::MotorDevice, motor_group::MotorGroup},
+    localization::localizer::Localizer,
+    runtime::{Instant, sleep},
+    subsystems::drivetrain::Drivetrain,
+};
+
+/// Measures what a motor group needs for `MotorController`'s `ks`, `kv` and
+/// `ka`. Each group is run through a slow voltage ramp and a voltage step,
+/// forward then backward, so a drivetrain ends up about where it started but
+/// needs room to drive in both directions.
+#[derive(Clone, Copy)]
+pub struct Characterization {
+    ramp_rate: f64,
+    ramp_duration: Duration,
+    step_voltage: f64,
+    step_duration: Duration,
+    rest: Duration,
+}
+
+impl Characterization {
+    // samples on either side used for the acceleration
+    const WINDOW: usize = 3;
+
+    pub fn new() -> Self {
+        Self {
+            ramp_rate: 1.0,
+            ramp_duration: Duration::from_secs(5),
+            step_voltage: 6.0,
+            step_duration: Duration::from_millis(1500),
+            rest: Duration::from_secs(1),
+        }
+    }
+
+    /// The quasistatic ramp in volts per second. Slow enough that the
+    /// acceleration stays near zero, which is what pins down `ks` and `kv`.
+    pub fn quasistatic(&mut self, ramp_rate: f64, duration: Duration) -> &mut Self {
+        self.ramp_rate = ramp_rate;
+        self.ramp_duration = duration;
+        self
+    }
+
+    /// The step in volts, which mostly measures `ka` while the group speeds
+    /// up.
+    pub fn step(&mut self, voltage: f64, duration: Duration) -> &mut Self {
+        self.step_voltage = voltage;
+        self.step_duration = duration;
+        self
+    }
+
+    /// How long the group is left to stop between tests.
+    pub fn rest(&mut self, duration: Duration) -> &mut Self {
+        self.rest = duration;
+        self
+    }
+
+    /// Characterizes a single group. The intake's motors belong to its task, so
+    /// they go through `Intake::characterize` instead.
+    pub async fn run<M: MotorDevice, C: FeedbackController>(
+        &self,
+        group: &mut MotorGroup<M, C>,
+    ) -> Vec<FeedforwardSample> {
+        let [samples] = self.characterize([group]).await;
+        samples
+    }
+
+    /// Characterizes both sides of the drivetrain at once while it drives
+    /// straight, left then right.
+    pub async fn run_drivetrain<M: MotorDevice, L: Localizer>(
+        &self,
+        dt: &mut Drivetrain<M, L>,
+    ) -> [Vec<FeedforwardSample>; 2] {
+        self.characterize([&mut dt.left, &mut dt.right]).await
+    }
+
+    /// Fits the samples and logs the constants under `name`.
+    pub fn fit(name: &str, samples: &[FeedforwardSample]) -> Option<Feedforward> {
+        let Some(feedforward) = Feedforward::fit(samples) else {
+            warn!("Couldn't fit a feedforward for {}", name);
+            return None;
+        };
+
+        let r_squared = feedforward.r_squared(samples).map_or_else(
+            || "unknown".to_owned(),
+            |r_squared| format!("{:.3}", r_squared),
+        );
+        info!(
+            "{} feedforward: ks {:.4}, kv {:.6}, ka {:.6}, r² {}",
+            name,
+            feedforward.ks(),
+            feedforward.kv(),
+            feedforward.ka(),
+            r_squared
+        );
+        Some(feedforward)
+    }
+
+    /// Writes samples to the SD card as CSV, to fit on the robot later or look
+    /// at on a computer.
+    pub fn save(path: &str, samples: &[FeedforwardSample]) -> io::Result<()> {
+        vexide::fs::write(path, to_csv(samples))
+    }
+
+    pub fn load(path: &str) -> Option<Vec<FeedforwardSample>> {
+        let bytes = vexide::fs::read(path)
+            .inspect_err(|err| warn!("Couldn't read samples {}: {:?}", path, err))
+            .ok()?;
+        from_csv(std::str::from_utf8(&bytes).ok()?)
+    }
+
+    async fn characterize<M: MotorDevice, C: FeedbackController, const N: usize>(
+        &self,
+        mut groups: [&mut MotorGroup<M, C>; N],
+    ) -> [Vec<FeedforwardSample>; N] {
+        let mut samples = [(); N].map(|_| Vec::new());
+
+        let tests = [(1.0, true), (-1.0, true), (1.0, false), (-1.0, false)];
+        for (direction, quasistatic) in tests {
+            let duration = if quasistatic {
+                self.ramp_duration
+            } else {
+                self.step_duration
+            };
+
+            // time in seconds, voltage and velocity for each group
+            let mut runs = [(); N].map(|_| Vec::new());
+            let start = Instant::now();
+            while start.elapsed() < duration {
+                let voltage = if quasistatic {
+                    self.ramp_rate * start.elapsed().as_secs_f64()
+                } else {
+                    self.step_voltage
+                };
+                let voltage = direction * voltage.min(Motor::V5_MAX_VOLTAGE);
+                for group in groups.iter_mut() {
+                    group.set_voltage(voltage);
+                }
+
+                sleep(Duration::from_millis(10)).await;
+
+                let time = start.elapsed().as_secs_f64();
+                for (group, run) in groups.iter().zip(runs.iter_mut()) {
+                    let velocity = group.velocity();
+                    if velocity.is_finite() {
+                        run.push((time, voltage, velocity));
+                    }
+                }
+            }
+
+            for group in groups.iter_mut() {
+                group.set_voltage(0.0);
+            }
+            for (run, samples) in runs.iter().zip(samples.iter_mut()) {
+                samples.extend(with_acceleration(run));
+            }
+
+            sleep(self.rest).await;
+        }
+
+        samples
+    }
+}
+
+impl Default for Characterization {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// differentiates the velocity over a few samples each way, one sample's
+// difference is mostly noise
+fn with_acceleration(run: &[(f64, f64, f64)]) -> impl Iterator<Item = FeedforwardSample> {
+    (0..run.len()).filter_map(|i| {
+        let before = run[i.saturating_sub(Characterization::WINDOW)];
+        let after = run[(i + Characterization::WINDOW).min(run.len() - 1)];
+        let elapsed = after.0 - before.0;
+        (elapsed > 0.0).then(|| FeedforwardSample {
+            voltage: run[i].1,
+            velocity: run[i].2,
+            acceleration: (after.2 - before.2) / elapsed,
+        })
+    })
+}
+
+pub fn to_csv(samples: &[FeedforwardSample]) -> String {
+    let mut csv = String::from("voltage,velocity,acceleration\n");
+    for sample in samples {
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            sample.voltage, sample.velocity, sample.acceleration
+        ));
+    }
+
+    csv
+}
+
+/// Reads samples written by `to_csv`, or `None` if a line isn't one.
+pub fn from_csv(csv: &str) -> Option<Vec<FeedforwardSample>> {
+    csv.lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split(',').map(|field| field.trim().parse::<f64>());
+            let sample = FeedforwardSample {
+                voltage: fields.next()?.ok()?,
+                velocity: fields.next()?.ok()?,
+                acceleration: fields.next()?.ok()?,
+            };
+            fields.next().is_none().then_some(sample)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_csv, to_csv};
+    use crate::controllers::feedforward::FeedforwardSample;
+
+    #[test]
+    fn csv_round_trips() {
+        let samples = [
+            FeedforwardSample {
+                voltage: 1.5,
+                velocity: 12.25,
+                acceleration: -0.1,
+            },
+            FeedforwardSample {
+                voltage: -12.0,
+                velocity: -587.123456789,
+                acceleration: 1e-7,
+            },
+        ];
+
+        let read = from_csv(&to_csv(&samples)).unwrap();
+
+        assert_eq!(read.len(), samples.len());
+        for (read, sample) in read.iter().zip(samples) {
+            assert_eq!(read.voltage, sample.voltage);
+            assert_eq!(read.velocity, sample.velocity);
+            assert_eq!(read.acceleration, sample.acceleration);
+        }
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        let header = "voltage,velocity,acceleration\n";
+
+        assert!(from_csv(&format!("{header}1,2\n")).is_none());
+        assert!(from_csv(&format!("{header}1,2,3,4\n")).is_none());
+        assert!(from_csv(&format!("{header}1,fast,3\n")).is_none());
+        assert_eq!(from_csv(header).unwrap().len(), 0);
+    }
+}
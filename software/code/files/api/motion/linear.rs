@@ -1,4 +1,4 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use log::{info};
 use uom::{
@@ -9,42 +9,66 @@ use uom::{
         time::second,
     },
 };
-use vexide::{prelude::Motor, time::sleep};
-
-use crate::{controllers::pid::Pid, localization::vec2::Vec2, subsystems::drivetrain::Drivetrain};
+use vexide::prelude::Motor;
+
+use crate::{
+    controllers::{FeedbackController, feedforward::Feedforward, pid::Pid},
+    hardware::device::MotorDevice,
+    localization::{localizer::Localizer, vec2::Vec2},
+    motion::profile::{Constraints, MotionProfile},
+    runtime::{Instant, sleep},
+    subsystems::drivetrain::Drivetrain,
+};
 
-pub struct Linear {
-    pid: Pid,
+pub struct Linear<C: FeedbackController = Pid> {
+    controller: C,
     tolerance: Length,
     velocity_tolerance: Option<Velocity>,
     timeout: Option<Duration>,
     speed: f64,
     tolerance_scale: f64,
+    profile: Option<(Constraints, Feedforward)>,
 }
 
-impl Linear {
-    pub fn new(pid: Pid, tolerance: Length) -> Self {
+impl<C: FeedbackController> Linear<C> {
+    pub fn new(controller: C, tolerance: Length) -> Self {
         Self {
-            pid,
+            controller,
             tolerance,
             velocity_tolerance: None,
             timeout: None,
             speed: Motor::V5_MAX_VOLTAGE,
             tolerance_scale: 1.0,
+            profile: None,
         }
     }
 
-    pub async fn drive_to_point(&mut self, dt: &mut Drivetrain, point: Vec2<Length>) {
+    pub async fn drive_to_point<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+        point: Vec2<Length>,
+    ) {
         let point = Vec2::new(point.x.get::<meter>(), point.y.get::<meter>());
         let pose = Vec2::new(dt.pose().x.get::<meter>(), dt.pose().y.get::<meter>());
         let target_distance = Length::new::<meter>((point - pose).length());
         self.drive_distance(dt, target_distance).await;
     }
 
-    pub async fn drive_distance(&mut self, dt: &mut Drivetrain, target: Length) {
+    pub async fn drive_distance<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+        target: Length,
+    ) {
         let mut time = Duration::ZERO;
         let mut prev_time = Instant::now();
         let mut traveled = Length::ZERO;
+        self.controller.reset();
+        let profile = self.profile.map(|(constraints, feedforward)| {
+            (
+                MotionProfile::new(target.get::<meter>(), constraints),
+                feedforward,
+            )
+        });
 
         loop {
             sleep(Duration::from_millis(10)).await;
@@ -56,12 +80,26 @@ impl Linear {
             let pose = dt.pose();
             traveled += pose.vf * Time::new::<second>(elapsed_time.as_secs_f64());
             let error = target - traveled;
-            let output = self
-                .pid
-                .output(error.get::<meter>(), elapsed_time)
-                .clamp(-self.speed, self.speed);
 
-            if self.is_settled(error, pose.vf, time) {
+            // a profiled drive follows the profile's setpoint instead of the target
+            let (setpoint, feedforward, profile_done) = match &profile {
+                Some((profile, feedforward)) => {
+                    let setpoint = profile.sample(time);
+                    (
+                        setpoint.position,
+                        feedforward.output(setpoint.velocity, setpoint.acceleration),
+                        time >= profile.duration(),
+                    )
+                }
+                None => (target.get::<meter>(), 0.0, true),
+            };
+            let output = (feedforward
+                + self
+                    .controller
+                    .update(setpoint, traveled.get::<meter>(), elapsed_time))
+            .clamp(-self.speed, self.speed);
+
+            if self.is_settled(error, pose.vf, time, profile_done) {
                 info!("Time: {}", time.as_millis());
                 break;
             }
@@ -77,8 +115,14 @@ impl Linear {
         dt.set_voltages(0.0, 0.0);
     }
 
-    fn is_settled(&mut self, error: Length, velocity: Velocity, time: Duration) -> bool {
-        let within_tolerance = error.abs() < self.tolerance * self.tolerance_scale;
+    fn is_settled(
+        &mut self,
+        error: Length,
+        velocity: Velocity,
+        time: Duration,
+        profile_done: bool,
+    ) -> bool {
+        let within_tolerance = profile_done && error.abs() < self.tolerance * self.tolerance_scale;
         let within_velocity = self.velocity_tolerance.is_none_or(|tolerance| velocity.abs() < tolerance);
         let timed_out = self.timeout.is_some_and(|timeout| time > timeout);
 
@@ -104,4 +148,16 @@ impl Linear {
         self.tolerance_scale = scale;
         self
     }
+
+    /// Follows a motion profile on every following drive, with the feedforward
+    /// in volts per meter per second.
+    pub fn profiled(&mut self, constraints: Constraints, feedforward: Feedforward) -> &mut Self {
+        self.profile = Some((constraints, feedforward));
+        self
+    }
+
+    pub fn unprofiled(&mut self) -> &mut Self {
+        self.profile = None;
+        self
+    }
 }
@@ -1,6 +1,13 @@
+pub mod autotune;
+pub mod characterization;
 pub mod linear;
 pub mod move_to;
+pub mod path;
+pub mod profile;
+pub mod pure_pursuit;
+pub mod ramsete;
 pub mod swing;
+pub mod trajectory;
 pub mod turn;
 
 pub fn desaturate<const N: usize>(values: [f64; N], max: f64) -> [f64; N] {
@@ -1,4 +1,4 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use log::{debug, info, warn};
 use uom::si::{
@@ -6,24 +6,38 @@ use uom::si::{
     f64::{Angle, Length, Velocity},
     length::meter,
 };
-use vexide::{prelude::Motor, time::sleep};
+use vexide::prelude::Motor;
 
 use crate::{
-    controllers::pid::Pid, localization::vec2::Vec2, motion::desaturate,
-    subsystems::drivetrain::Drivetrain, utils::wrap,
+    controllers::{FeedbackController, pid::Pid},
+    hardware::device::MotorDevice,
+    localization::{localizer::Localizer, mirror::Mirror, pose::Pose, vec2::Vec2},
+    motion::desaturate,
+    runtime::{Instant, sleep},
+    subsystems::drivetrain::Drivetrain,
+    utils::wrap,
 };
 
-pub struct MoveTo {
-    linear: Pid,
-    sideways: Pid,
+pub struct MoveTo<C: FeedbackController = Pid> {
+    linear: C,
+    sideways: C,
     tolerance: Length,
     velocity_tolerance: Option<Velocity>,
     timeout: Option<Duration>,
     tolerance_scale: f64,
+    lead: f64,
+    settle_radius: Length,
+    heading_tolerance: Angle,
+    reverse: bool,
+    mirror: Option<Mirror>,
 }
 
-impl MoveTo {
-    pub fn new(linear: Pid, sideways: Pid, tolerance: Length) -> Self {
+impl<C: FeedbackController> MoveTo<C> {
+    const DEFAULT_LEAD: f64 = 0.6;
+    const DEFAULT_SETTLE_RADIUS: f64 = 0.15; // meters
+    const DEFAULT_HEADING_TOLERANCE: f64 = 0.05; // radians
+
+    pub fn new(linear: C, sideways: C, tolerance: Length) -> Self {
         Self {
             linear,
             sideways,
@@ -31,11 +45,26 @@ impl MoveTo {
             velocity_tolerance: None,
             timeout: None,
             tolerance_scale: 1.0,
+            lead: Self::DEFAULT_LEAD,
+            settle_radius: Length::new::<meter>(Self::DEFAULT_SETTLE_RADIUS),
+            heading_tolerance: Angle::new::<radian>(Self::DEFAULT_HEADING_TOLERANCE),
+            reverse: false,
+            mirror: None,
         }
     }
 
-    pub async fn move_to_point(&mut self, dt: &mut Drivetrain, target: Vec2<Length>) {
+    pub async fn move_to_point<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+        target: Vec2<Length>,
+    ) {
+        let target = self
+            .mirror
+            .as_ref()
+            .map_or(target, |mirror| mirror.point(target));
         let start_time = Instant::now();
+        self.linear.reset();
+        self.sideways.reset();
         let mut prev_time = Instant::now();
         debug!("attempting to go to: {:?}", target);
 
@@ -103,6 +132,115 @@ impl MoveTo {
         dt.set_voltages(0.0, 0.0);
     }
 
+    /// Drives to the target position and arrives facing the target heading by
+    /// chasing a carrot point placed behind the target along its heading. The
+    /// carrot slides onto the target as the robot gets closer, and inside the
+    /// settle radius the robot steers toward the target heading itself, since
+    /// the direction to a point it's nearly on top of is mostly noise.
+    pub async fn move_to_pose<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+        target: Pose,
+    ) {
+        let target = self
+            .mirror
+            .as_ref()
+            .map_or(target, |mirror| mirror.pose(target));
+        let start_time = Instant::now();
+        self.linear.reset();
+        self.sideways.reset();
+        let mut prev_time = Instant::now();
+        debug!("attempting to go to: ({})", target);
+
+        // the direction the robot is traveling when it reaches the target
+        let approach = if self.reverse {
+            target.h + Angle::HALF_TURN
+        } else {
+            target.h
+        };
+        let (approach_sin, approach_cos) = approach.get::<radian>().sin_cos();
+        let target_point = Vec2::new(target.x.get::<meter>(), target.y.get::<meter>());
+        let mut settling = false;
+
+        loop {
+            sleep(Duration::from_millis(10)).await;
+            let elapsed_time = prev_time.elapsed();
+            prev_time = Instant::now();
+
+            let pose = dt.pose();
+            let position = Vec2::new(pose.x.get::<meter>(), pose.y.get::<meter>());
+            let remaining = target_point.distance(position);
+            let heading_error = wrap(target.h - pose.h);
+
+            if remaining < self.tolerance.get::<meter>() * self.tolerance_scale
+                && heading_error.abs() < self.heading_tolerance
+                && (self
+                    .velocity_tolerance
+                    .is_none_or(|tolerance| pose.vf.abs() < tolerance))
+            {
+                info!("move to pose success");
+                break;
+            }
+
+            if self
+                .timeout
+                .is_some_and(|timeout| start_time.elapsed() > timeout)
+            {
+                warn!("Moving failed");
+                break;
+            }
+
+            let carrot =
+                target_point - Vec2::new(approach_cos, approach_sin) * (self.lead * remaining);
+            let position_error = carrot - position;
+            let distance = position_error.length();
+            let target_h = Angle::new::<radian>(position_error.angle());
+
+            let herror = wrap(target_h - pose.h).get::<radian>();
+            let mut projected_cte = distance * herror.sin();
+            // signed so that overshooting the carrot backs the robot up
+            let projected_distance = distance * herror.cos();
+
+            if self.reverse {
+                projected_cte *= -1.0;
+            }
+
+            // stays settled once inside so the robot doesn't switch back and
+            // forth at the edge
+            if !settling && remaining < self.settle_radius.get::<meter>() {
+                settling = true;
+                self.sideways.reset();
+            }
+            if settling {
+                // the arc the heading error sweeps at the settle radius, which
+                // keeps the sideways gains in the same units
+                projected_cte = heading_error.get::<radian>() * self.settle_radius.get::<meter>();
+            }
+
+            let angular_output = self.sideways.output(-projected_cte, elapsed_time);
+            let linear_output = self.linear.output(projected_distance, elapsed_time);
+
+            debug!("Position: ({})", pose);
+
+            let [left, right] = desaturate(
+                [
+                    linear_output + angular_output,
+                    linear_output - angular_output,
+                ],
+                Motor::V5_MAX_VOLTAGE,
+            );
+
+            dt.set_voltages(left, right);
+        }
+
+        self.timeout = None;
+        self.tolerance_scale = 1.0;
+        self.velocity_tolerance = None;
+        self.reverse = false;
+
+        dt.set_voltages(0.0, 0.0);
+    }
+
     pub fn timeout(&mut self, duration: Duration) -> &mut Self {
         self.timeout = Some(duration);
         self
@@ -117,4 +255,41 @@ impl MoveTo {
         self.velocity_tolerance = Some(velocity);
         self
     }
+
+    /// How far behind the target the carrot starts, as a fraction of the
+    /// remaining distance. Higher values make wider arcs into the target.
+    pub fn lead(&mut self, lead: f64) -> &mut Self {
+        self.lead = lead;
+        self
+    }
+
+    /// How close to the target the robot stops chasing the carrot and turns to
+    /// the target heading instead.
+    pub fn settle_radius(&mut self, radius: Length) -> &mut Self {
+        self.settle_radius = radius;
+        self
+    }
+
+    /// How far from the target heading `move_to_pose` can finish.
+    pub fn heading_tolerance(&mut self, tolerance: Angle) -> &mut Self {
+        self.heading_tolerance = tolerance;
+        self
+    }
+
+    /// Approaches the next pose driving backwards.
+    pub fn reverse(&mut self) -> &mut Self {
+        self.reverse = true;
+        self
+    }
+
+    /// Reflects every following target onto the robot's alliance side.
+    pub fn mirrored(&mut self, mirror: Mirror) -> &mut Self {
+        self.mirror = Some(mirror);
+        self
+    }
+
+    pub fn unmirrored(&mut self) -> &mut Self {
+        self.mirror = None;
+        self
+    }
 }
@@ -0,0 +1,405 @@
+use std::time::Duration;
+
+use log::warn;
+use uom::si::{
+    acceleration::meter_per_second_squared,
+    angle::radian,
+    angular_velocity::radian_per_second,
+    f64::{Acceleration, Angle, AngularVelocity, Length, Velocity},
+    length::meter,
+    velocity::meter_per_second,
+};
+
+use crate::{
+    localization::{pose::Pose, vec2::Vec2},
+    motion::trajectory::{Trajectory, TrajectoryPoint},
+};
+
+// parameter steps per segment used to measure arc length before resampling
+const SUBDIVISIONS: usize = 200;
+
+#[derive(Clone, Copy)]
+pub enum Spline {
+    /// Control points are laid out as `start, handle, handle, end` with each end
+    /// shared by the next curve, so `3n + 1` points make `n` curves. Any other
+    /// number of points makes an empty trajectory.
+    CubicBezier,
+    /// Passes through every point with tangents taken from the neighbors and no
+    /// curvature at the points.
+    QuinticHermite,
+    /// Passes through every point with tangents taken from the neighbors.
+    CatmullRom,
+}
+
+/// Turns control points into a trajectory sampled at a fixed spacing, with
+/// velocities limited by the max speed, max acceleration and how hard the
+/// outside wheel has to work through each curve.
+pub struct PathGenerator {
+    spacing: Length,
+    max_velocity: Velocity,
+    max_acceleration: Acceleration,
+    track: Length,
+}
+
+impl PathGenerator {
+    pub fn new(
+        spacing: Length,
+        max_velocity: Velocity,
+        max_acceleration: Acceleration,
+        track: Length,
+    ) -> Self {
+        Self {
+            spacing,
+            max_velocity,
+            max_acceleration,
+            track,
+        }
+    }
+
+    pub fn generate(&self, spline: Spline, control_points: &[Vec2<Length>]) -> Trajectory {
+        let points: Vec<Vec2<f64>> = control_points
+            .iter()
+            .map(|point| Vec2::new(point.x.get::<meter>(), point.y.get::<meter>()))
+            .collect();
+
+        let dense = match spline {
+            Spline::CubicBezier => bezier(&points),
+            Spline::QuinticHermite => hermite(&points),
+            Spline::CatmullRom => catmull_rom(&points),
+        };
+
+        self.parameterize(&self.resample(&dense))
+    }
+
+    // walks the densely sampled curve and drops a point every `spacing` meters
+    fn resample(&self, dense: &[Vec2<f64>]) -> Vec<Vec2<f64>> {
+        let Some(&first) = dense.first() else {
+            return Vec::new();
+        };
+
+        let spacing = self.spacing.get::<meter>();
+        let mut samples = vec![first];
+        let mut since_sample = 0.0;
+
+        for pair in dense.windows(2) {
+            let step = pair[1].distance(pair[0]);
+            since_sample += step;
+
+            if since_sample >= spacing {
+                samples.push(pair[1]);
+                since_sample = 0.0;
+            }
+        }
+
+        // the end always lands on the path's last point, replacing the final
+        // sample if it would leave a sliver of a segment
+        let last = dense[dense.len() - 1];
+        if since_sample > spacing / 2.0 || samples.len() == 1 {
+            samples.push(last);
+        } else if let Some(sample) = samples.last_mut() {
+            *sample = last;
+        }
+
+        samples
+    }
+
+    fn parameterize(&self, samples: &[Vec2<f64>]) -> Trajectory {
+        // a single segment starts and ends at rest so it would never get
+        // moving, the midpoint gives it somewhere to speed up to
+        if let [start, end] = *samples {
+            return self.parameterize(&[start, (start + end) * 0.5, end]);
+        }
+
+        let count = samples.len();
+        if count < 2 {
+            return Trajectory::new(
+                samples
+                    .iter()
+                    .map(|point| TrajectoryPoint {
+                        time: Duration::ZERO,
+                        pose: Pose::new(
+                            Length::new::<meter>(point.x),
+                            Length::new::<meter>(point.y),
+                            Angle::new::<radian>(0.0),
+                        ),
+                    })
+                    .collect(),
+            );
+        }
+
+        let max_velocity = self.max_velocity.get::<meter_per_second>();
+        let max_acceleration = self.max_acceleration.get::<meter_per_second_squared>();
+        let half_track = self.track.get::<meter>() / 2.0;
+
+        let headings: Vec<f64> = (0..count)
+            .map(|i| (samples[(i + 1).min(count - 1)] - samples[i.max(1) - 1]).angle())
+            .collect();
+        let curvatures: Vec<f64> = (0..count)
+            .map(|i| {
+                if i == 0 || i == count - 1 {
+                    0.0
+                } else {
+                    curvature(samples[i - 1], samples[i], samples[i + 1])
+                }
+            })
+            .collect();
+        let distances: Vec<f64> = samples
+            .windows(2)
+            .map(|pair| pair[1].distance(pair[0]))
+            .collect();
+
+        // slowing down through curves keeps the outside wheel under max speed
+        let mut velocities: Vec<f64> = curvatures
+            .iter()
+            .map(|curvature| max_velocity / (1.0 + curvature.abs() * half_track))
+            .collect();
+        velocities[0] = 0.0;
+        velocities[count - 1] = 0.0;
+
+        for i in 1..count {
+            let reachable =
+                (velocities[i - 1].powi(2) + 2.0 * max_acceleration * distances[i - 1]).sqrt();
+            velocities[i] = velocities[i].min(reachable);
+        }
+        for i in (0..count - 1).rev() {
+            let reachable =
+                (velocities[i + 1].powi(2) + 2.0 * max_acceleration * distances[i]).sqrt();
+            velocities[i] = velocities[i].min(reachable);
+        }
+
+        let mut time = 0.0;
+        let mut points = Vec::with_capacity(count);
+        for i in 0..count {
+            if i > 0 {
+                let average = (velocities[i - 1] + velocities[i]) / 2.0;
+                if average > 0.0 {
+                    time += distances[i - 1] / average;
+                }
+            }
+
+            points.push(TrajectoryPoint {
+                time: Duration::from_secs_f64(time),
+                pose: Pose {
+                    x: Length::new::<meter>(samples[i].x),
+                    y: Length::new::<meter>(samples[i].y),
+                    h: Angle::new::<radian>(headings[i]),
+                    vf: Velocity::new::<meter_per_second>(velocities[i]),
+                    vs: Velocity::new::<meter_per_second>(0.0),
+                    omega: AngularVelocity::new::<radian_per_second>(velocities[i] * curvatures[i]),
+                },
+            });
+        }
+
+        Trajectory::new(points)
+    }
+}
+
+// signed curvature of the circle through three points, positive turning left
+fn curvature(a: Vec2<f64>, b: Vec2<f64>, c: Vec2<f64>) -> f64 {
+    let ab = b - a;
+    let bc = c - b;
+    let ca = a - c;
+    let denominator = ab.length() * bc.length() * ca.length();
+
+    if denominator == 0.0 {
+        0.0
+    } else {
+        2.0 * ab.cross(bc) / denominator
+    }
+}
+
+fn sample_segments(segments: usize, point: impl Fn(usize, f64) -> Vec2<f64>) -> Vec<Vec2<f64>> {
+    let mut dense = Vec::with_capacity(segments * SUBDIVISIONS + 1);
+
+    for segment in 0..segments {
+        for step in 0..SUBDIVISIONS {
+            dense.push(point(segment, step as f64 / SUBDIVISIONS as f64));
+        }
+    }
+    if segments > 0 {
+        dense.push(point(segments - 1, 1.0));
+    }
+
+    dense
+}
+
+fn bezier(points: &[Vec2<f64>]) -> Vec<Vec2<f64>> {
+    if points.len() < 4 || !(points.len() - 1).is_multiple_of(3) {
+        warn!(
+            "Bezier paths need 3n + 1 control points, got {}",
+            points.len()
+        );
+        return Vec::new();
+    }
+
+    sample_segments((points.len() - 1) / 3, |segment, t| {
+        let [p0, p1, p2, p3] = [0, 1, 2, 3].map(|i| points[segment * 3 + i]);
+        let u = 1.0 - t;
+
+        p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t)
+    })
+}
+
+// tangent at a point from its neighbors, clamped at the ends of the path
+fn tangent(points: &[Vec2<f64>], i: usize) -> Vec2<f64> {
+    let before = points[i.saturating_sub(1)];
+    let after = points[(i + 1).min(points.len() - 1)];
+    let span = if i == 0 || i == points.len() - 1 {
+        1.0
+    } else {
+        2.0
+    };
+
+    (after - before) * (1.0 / span)
+}
+
+fn hermite(points: &[Vec2<f64>]) -> Vec<Vec2<f64>> {
+    if points.len() < 2 {
+        return points.to_vec();
+    }
+
+    sample_segments(points.len() - 1, |segment, t| {
+        let (p0, p1) = (points[segment], points[segment + 1]);
+        let (v0, v1) = (tangent(points, segment), tangent(points, segment + 1));
+
+        let t3 = t * t * t;
+        let t4 = t3 * t;
+        let t5 = t4 * t;
+
+        // quintic basis with the second derivatives at both ends left at zero
+        let h0 = 1.0 - 10.0 * t3 + 15.0 * t4 - 6.0 * t5;
+        let h1 = t - 6.0 * t3 + 8.0 * t4 - 3.0 * t5;
+        let h4 = -4.0 * t3 + 7.0 * t4 - 3.0 * t5;
+        let h5 = 10.0 * t3 - 15.0 * t4 + 6.0 * t5;
+
+        p0 * h0 + v0 * h1 + v1 * h4 + p1 * h5
+    })
+}
+
+fn catmull_rom(points: &[Vec2<f64>]) -> Vec<Vec2<f64>> {
+    if points.len() < 2 {
+        return points.to_vec();
+    }
+
+    sample_segments(points.len() - 1, |segment, t| {
+        let p0 = points[segment.saturating_sub(1)];
+        let p1 = points[segment];
+        let p2 = points[segment + 1];
+        let p3 = points[(segment + 2).min(points.len() - 1)];
+
+        let t2 = t * t;
+        let t3 = t2 * t;
+
+        (p1 * 2.0
+            + (p2 - p0) * t
+            + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+            + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+            * 0.5
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uom::si::{
+        acceleration::meter_per_second_squared,
+        f64::{Acceleration, Length, Velocity},
+        length::{inch, meter},
+        velocity::meter_per_second,
+    };
+
+    use super::{PathGenerator, SUBDIVISIONS, Spline, bezier, catmull_rom, hermite, tangent};
+    use crate::localization::vec2::Vec2;
+
+    fn points() -> Vec<Vec2<f64>> {
+        [(0.0, 0.0), (1.0, 0.0), (2.0, 1.0), (2.0, 2.0)]
+            .map(|(x, y)| Vec2::new(x, y))
+            .to_vec()
+    }
+
+    fn assert_point(actual: Vec2<f64>, expected: Vec2<f64>, tolerance: f64) {
+        assert!(
+            actual.distance(expected) < tolerance,
+            "expected ({}, {}), got ({}, {})",
+            expected.x,
+            expected.y,
+            actual.x,
+            actual.y
+        );
+    }
+
+    // the curve passes through every point with the tangent from its neighbors
+    fn assert_interpolates(dense: &[Vec2<f64>], points: &[Vec2<f64>]) {
+        assert_eq!(dense.len(), (points.len() - 1) * SUBDIVISIONS + 1);
+
+        for (i, &point) in points.iter().enumerate() {
+            assert_point(dense[i * SUBDIVISIONS], point, 1e-9);
+        }
+        for i in 1..points.len() - 1 {
+            let at = i * SUBDIVISIONS;
+            // central difference over one parameter step each way, which is
+            // only first order since the curvature jumps at the point
+            let derivative = (dense[at + 1] - dense[at - 1]) * (SUBDIVISIONS as f64 / 2.0);
+            assert_point(derivative, tangent(points, i), 1e-2);
+        }
+    }
+
+    #[test]
+    fn hermite_passes_through_points_along_their_tangents() {
+        let points = points();
+        assert_interpolates(&hermite(&points), &points);
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_points_along_their_tangents() {
+        let points = points();
+        assert_interpolates(&catmull_rom(&points), &points);
+    }
+
+    #[test]
+    fn bezier_ends_on_its_end_points() {
+        let points = points();
+        let dense = bezier(&points);
+
+        assert_point(dense[0], points[0], 1e-9);
+        assert_point(dense[dense.len() - 1], points[3], 1e-9);
+        // leaves the start toward the first handle
+        let direction = dense[1] - dense[0];
+        assert!(direction.x > 0.0, "{}", direction.x);
+        assert!(direction.y.abs() < direction.x * 1e-2, "{}", direction.y);
+    }
+
+    #[test]
+    fn bezier_needs_three_n_plus_one_points() {
+        let points = points();
+
+        assert!(bezier(&points[..3]).is_empty());
+        assert!(bezier(&[points.clone(), points[..2].to_vec()].concat()).is_empty());
+    }
+
+    #[test]
+    fn single_segment_still_gets_moving() {
+        let generator = PathGenerator::new(
+            Length::new::<inch>(1.0),
+            Velocity::new::<meter_per_second>(1.0),
+            Acceleration::new::<meter_per_second_squared>(2.0),
+            Length::new::<inch>(12.0),
+        );
+        let end = Vec2::new(Length::new::<meter>(0.03), Length::new::<meter>(0.0));
+
+        let trajectory = generator.generate(
+            Spline::CatmullRom,
+            &[Vec2::new(Length::default(), Length::default()), end],
+        );
+
+        // a triangle profile over 3 cm at 2 m/s², sqrt(0.03 / 2) each way
+        let expected = Duration::from_secs_f64(2.0 * (0.03f64 / 2.0).sqrt());
+        let difference = trajectory.duration().abs_diff(expected);
+        assert!(
+            difference < Duration::from_millis(1),
+            "{:?}",
+            trajectory.duration()
+        );
+    }
+}
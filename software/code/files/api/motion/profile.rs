@@ -0,0 +1,310 @@
+use std::time::Duration;
+
+/// Limits for generating a profile, in meters or radians and their per-second
+/// derivatives. A profile without a jerk limit is trapezoidal, one with a jerk
+/// limit is an S-curve.
+#[derive(Clone, Copy)]
+pub struct Constraints {
+    pub velocity: f64,
+    pub acceleration: f64,
+    pub jerk: Option<f64>,
+}
+
+impl Constraints {
+    pub const fn trapezoidal(velocity: f64, acceleration: f64) -> Self {
+        Self {
+            velocity,
+            acceleration,
+            jerk: None,
+        }
+    }
+
+    pub const fn s_curve(velocity: f64, acceleration: f64, jerk: f64) -> Self {
+        Self {
+            velocity,
+            acceleration,
+            jerk: Some(jerk),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProfileState {
+    pub position: f64,
+    pub velocity: f64,
+    pub acceleration: f64,
+}
+
+// A stretch of the profile with constant jerk, trapezoidal profiles just use a
+// jerk of zero and jump the acceleration between segments.
+#[derive(Clone, Copy, Default)]
+struct Segment {
+    duration: f64,
+    jerk: f64,
+    start: ProfileState,
+}
+
+impl Segment {
+    fn sample(&self, t: f64) -> ProfileState {
+        let ProfileState {
+            position,
+            velocity,
+            acceleration,
+        } = self.start;
+
+        ProfileState {
+            position: position
+                + velocity * t
+                + acceleration * t * t / 2.0
+                + self.jerk * t * t * t / 6.0,
+            velocity: velocity + acceleration * t + self.jerk * t * t / 2.0,
+            acceleration: acceleration + self.jerk * t,
+        }
+    }
+}
+
+/// A rest to rest motion profile covering a set distance.
+pub struct MotionProfile {
+    segments: Vec<Segment>,
+    sign: f64,
+    duration: f64,
+}
+
+impl MotionProfile {
+    pub fn new(distance: f64, constraints: Constraints) -> Self {
+        let sign = distance.signum();
+        let distance = distance.abs();
+        let Constraints {
+            velocity,
+            acceleration,
+            jerk,
+        } = constraints;
+
+        // (duration, jerk, acceleration) for each stretch of the profile
+        let phases = match jerk {
+            None => Self::trapezoidal_phases(distance, velocity, acceleration),
+            Some(jerk) => Self::s_curve_phases(distance, velocity, acceleration, jerk),
+        };
+
+        let mut segments = Vec::with_capacity(phases.len());
+        let mut state = ProfileState::default();
+        for (duration, jerk, acceleration) in phases {
+            // also skips the NaN durations left by a zero length profile
+            if duration.is_nan() || duration <= 0.0 {
+                continue;
+            }
+
+            state.acceleration = acceleration;
+            let segment = Segment {
+                duration,
+                jerk,
+                start: state,
+            };
+            state = segment.sample(duration);
+            segments.push(segment);
+        }
+
+        Self {
+            duration: segments.iter().map(|segment| segment.duration).sum(),
+            segments,
+            sign,
+        }
+    }
+
+    fn trapezoidal_phases(distance: f64, velocity: f64, acceleration: f64) -> Vec<(f64, f64, f64)> {
+        // falls back to a triangle when there is no room to reach max velocity
+        let peak = velocity.min((distance * acceleration).sqrt());
+        let ramp = peak / acceleration;
+        let cruise = (distance - peak * ramp) / peak;
+
+        vec![
+            (ramp, 0.0, acceleration),
+            (cruise, 0.0, 0.0),
+            (ramp, 0.0, -acceleration),
+        ]
+    }
+
+    fn s_curve_phases(
+        distance: f64,
+        velocity: f64,
+        acceleration: f64,
+        jerk: f64,
+    ) -> Vec<(f64, f64, f64)> {
+        // highest velocity reachable over half the distance with acceleration
+        // limited, then with only jerk limiting if that turns out too short to
+        // ever reach max acceleration
+        let mut peak = acceleration
+            * (-(acceleration / jerk)
+                + ((acceleration / jerk).powi(2) + 4.0 * distance / acceleration).sqrt())
+            / 2.0;
+        if peak * jerk < acceleration * acceleration {
+            peak = (distance * jerk.sqrt() / 2.0).powf(2.0 / 3.0);
+        }
+        let peak = peak.min(velocity);
+
+        // time spent changing acceleration and holding it
+        let (ramp, hold, peak_acceleration) = if peak * jerk < acceleration * acceleration {
+            let ramp = (peak / jerk).sqrt();
+            (ramp, 0.0, jerk * ramp)
+        } else {
+            let ramp = acceleration / jerk;
+            (ramp, peak / acceleration - ramp, acceleration)
+        };
+
+        let accelerating = peak * (2.0 * ramp + hold) / 2.0;
+        let cruise = (distance - 2.0 * accelerating) / peak;
+
+        vec![
+            (ramp, jerk, 0.0),
+            (hold, 0.0, peak_acceleration),
+            (ramp, -jerk, peak_acceleration),
+            (cruise, 0.0, 0.0),
+            (ramp, -jerk, 0.0),
+            (hold, 0.0, -peak_acceleration),
+            (ramp, jerk, -peak_acceleration),
+        ]
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.duration)
+    }
+
+    pub fn sample(&self, time: Duration) -> ProfileState {
+        let mut t = time.as_secs_f64();
+
+        for segment in self.segments.iter() {
+            if t <= segment.duration {
+                return self.signed(segment.sample(t));
+            }
+            t -= segment.duration;
+        }
+
+        let end = self
+            .segments
+            .last()
+            .map(|segment| segment.sample(segment.duration))
+            .unwrap_or_default();
+
+        self.signed(ProfileState {
+            position: end.position,
+            velocity: 0.0,
+            acceleration: 0.0,
+        })
+    }
+
+    fn signed(&self, state: ProfileState) -> ProfileState {
+        ProfileState {
+            position: state.position * self.sign,
+            velocity: state.velocity * self.sign,
+            acceleration: state.acceleration * self.sign,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Constraints, MotionProfile, ProfileState};
+
+    fn at(profile: &MotionProfile, seconds: f64) -> ProfileState {
+        profile.sample(Duration::from_secs_f64(seconds))
+    }
+
+    fn assert_close(name: &str, actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {name} {expected}, got {actual}"
+        );
+    }
+
+    // steps through the profile checking it never breaks its constraints
+    fn assert_within(profile: &MotionProfile, constraints: Constraints) {
+        let steps = (profile.duration().as_secs_f64() / 0.001) as usize;
+        let mut previous = at(profile, 0.0);
+        for step in 1..=steps {
+            let state = at(profile, step as f64 * 0.001);
+            assert!(state.position >= previous.position - 1e-9, "{state:?}");
+            assert!(state.velocity <= constraints.velocity + 1e-9, "{state:?}");
+            assert!(
+                state.acceleration.abs() <= constraints.acceleration + 1e-9,
+                "{state:?}"
+            );
+            previous = state;
+        }
+    }
+
+    #[test]
+    fn trapezoid_cruises_at_max_velocity() {
+        let constraints = Constraints::trapezoidal(1.0, 2.0);
+        let profile = MotionProfile::new(2.0, constraints);
+
+        // half a second each way to reach 1 m/s covers 0.5 m, the other 1.5 m
+        // takes 1.5 s at full speed
+        assert_close("duration", profile.duration().as_secs_f64(), 2.5);
+        assert_close("peak velocity", at(&profile, 1.25).velocity, 1.0);
+        assert_close("acceleration", at(&profile, 0.25).acceleration, 2.0);
+        assert_close("end", at(&profile, 2.5).position, 2.0);
+        assert_within(&profile, constraints);
+    }
+
+    #[test]
+    fn short_trapezoid_becomes_a_triangle() {
+        let profile = MotionProfile::new(1.0, Constraints::trapezoidal(10.0, 1.0));
+
+        assert_close("duration", profile.duration().as_secs_f64(), 2.0);
+        assert_close("peak velocity", at(&profile, 1.0).velocity, 1.0);
+        assert_close("end", at(&profile, 2.0).position, 1.0);
+    }
+
+    #[test]
+    fn s_curve_limits_jerk() {
+        let constraints = Constraints::s_curve(2.0, 2.0, 4.0);
+        let profile = MotionProfile::new(10.0, constraints);
+
+        // 0.5 s ramping the acceleration up and down and 0.5 s holding it on
+        // each end covers 1.5 m, leaving 3.5 s of cruise
+        assert_close("duration", profile.duration().as_secs_f64(), 6.5);
+        assert_close("peak velocity", at(&profile, 3.25).velocity, 2.0);
+        assert_close("acceleration", at(&profile, 0.25).acceleration, 1.0);
+        assert_close("start acceleration", at(&profile, 0.0).acceleration, 0.0);
+        assert_close("end", at(&profile, 6.5).position, 10.0);
+        assert_within(&profile, constraints);
+    }
+
+    #[test]
+    fn short_s_curve_never_reaches_max_acceleration() {
+        let constraints = Constraints::s_curve(2.0, 2.0, 4.0);
+        let profile = MotionProfile::new(0.1, constraints);
+
+        assert_close("end", at(&profile, 10.0).position, 0.1);
+        assert_within(&profile, constraints);
+    }
+
+    #[test]
+    fn zero_distance_is_already_done() {
+        for constraints in [
+            Constraints::trapezoidal(1.0, 2.0),
+            Constraints::s_curve(1.0, 2.0, 4.0),
+        ] {
+            let profile = MotionProfile::new(0.0, constraints);
+
+            assert_eq!(profile.duration(), Duration::ZERO);
+            assert_eq!(at(&profile, 1.0), ProfileState::default());
+        }
+    }
+
+    #[test]
+    fn negative_distance_runs_backwards() {
+        let constraints = Constraints::trapezoidal(1.0, 2.0);
+        let forward = MotionProfile::new(2.0, constraints);
+        let backward = MotionProfile::new(-2.0, constraints);
+
+        assert_eq!(forward.duration(), backward.duration());
+        for seconds in [0.25, 1.25, 2.4] {
+            let (forward, backward) = (at(&forward, seconds), at(&backward, seconds));
+            assert_close("position", backward.position, -forward.position);
+            assert_close("velocity", backward.velocity, -forward.velocity);
+        }
+    }
+}
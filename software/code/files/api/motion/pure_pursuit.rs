@@ -0,0 +1,299 @@
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use uom::si::{
+    angle::radian,
+    f64::{Length, Velocity},
+    length::meter,
+    ratio::ratio,
+    velocity::meter_per_second,
+};
+use vexide::prelude::Gearset;
+
+use crate::{
+    hardware::device::MotorDevice,
+    localization::{localizer::Localizer, vec2::Vec2},
+    motion::desaturate,
+    runtime::{Instant, sleep},
+    subsystems::drivetrain::Drivetrain,
+};
+
+#[derive(Clone, Copy)]
+pub struct Waypoint {
+    pub point: Vec2<Length>,
+    /// Speed to drive along the segment ending at this point, uses the max
+    /// speed of the follower if not given.
+    pub speed: Option<Velocity>,
+}
+
+impl Waypoint {
+    pub fn new(point: Vec2<Length>, speed: Option<Velocity>) -> Self {
+        Self { point, speed }
+    }
+}
+
+impl From<Vec2<Length>> for Waypoint {
+    fn from(point: Vec2<Length>) -> Self {
+        Self { point, speed: None }
+    }
+}
+
+pub struct PurePursuit {
+    max_speed: Velocity,
+    min_lookahead: Length,
+    max_lookahead: Length,
+    tolerance: Length,
+    velocity_tolerance: Option<Velocity>,
+    timeout: Option<Duration>,
+}
+
+impl PurePursuit {
+    // keeps the robot creeping forward at the end of the path instead of stalling
+    const MIN_SPEED_SCALE: f64 = 0.2;
+
+    pub fn new(
+        max_speed: Velocity,
+        min_lookahead: Length,
+        max_lookahead: Length,
+        tolerance: Length,
+    ) -> Self {
+        Self {
+            max_speed,
+            min_lookahead,
+            max_lookahead,
+            tolerance,
+            velocity_tolerance: None,
+            timeout: None,
+        }
+    }
+
+    pub async fn follow<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+        path: &[Waypoint],
+    ) {
+        let Some(end) = path.last() else {
+            warn!("Pure pursuit given an empty path");
+            return;
+        };
+        let end = to_meters(end.point);
+
+        let start_time = Instant::now();
+        let mut segment = 0;
+        let mut lookahead_point = to_meters(path[0].point);
+
+        loop {
+            sleep(Duration::from_millis(10)).await;
+
+            let pose = dt.pose();
+            let position = Vec2::new(pose.x.get::<meter>(), pose.y.get::<meter>());
+            let remaining = end.distance(position);
+
+            if remaining < self.tolerance.get::<meter>()
+                && self
+                    .velocity_tolerance
+                    .is_none_or(|tolerance| pose.vf.abs() < tolerance)
+            {
+                info!("Path complete in {}ms", start_time.elapsed().as_millis());
+                break;
+            }
+
+            if self
+                .timeout
+                .is_some_and(|timeout| start_time.elapsed() > timeout)
+            {
+                warn!("Path interrupted {:.2}m from the end", remaining);
+                break;
+            }
+
+            // the lookahead grows with speed to smooth out fast sections
+            let speed_ratio = (pose.vf.abs() / self.max_speed)
+                .get::<ratio>()
+                .clamp(0.0, 1.0);
+            let lookahead = (self.min_lookahead
+                + (self.max_lookahead - self.min_lookahead) * speed_ratio)
+                .get::<meter>();
+
+            if remaining < lookahead {
+                lookahead_point = end;
+                segment = path.len().saturating_sub(2);
+            } else if let Some((index, point)) = find_lookahead(path, segment, position, lookahead)
+            {
+                segment = index;
+                lookahead_point = point;
+            }
+
+            let curvature = curvature(position, pose.h.get::<radian>(), lookahead_point);
+
+            let target_speed = path
+                .get(segment + 1)
+                .and_then(|waypoint| waypoint.speed)
+                .unwrap_or(self.max_speed)
+                .min(self.max_speed);
+            let slowdown = (remaining / lookahead).clamp(Self::MIN_SPEED_SCALE, 1.0);
+            let speed = target_speed.get::<meter_per_second>() * slowdown;
+
+            debug!("Position: ({}), curvature: {:.3}", pose, curvature);
+
+            let half_track = dt.track().get::<meter>() / 2.0;
+            let to_rpm = 60.0 / dt.wheel_circum().get::<meter>();
+            let [left, right] = desaturate(
+                [
+                    speed * (1.0 - curvature * half_track) * to_rpm,
+                    speed * (1.0 + curvature * half_track) * to_rpm,
+                ],
+                Gearset::MAX_BLUE_RPM,
+            );
+
+            dt.set_velocity(left, right);
+        }
+
+        self.velocity_tolerance = None;
+        self.timeout = None;
+
+        dt.set_voltages(0.0, 0.0);
+    }
+
+    pub fn settle_velocity(&mut self, velocity: Velocity) -> &mut Self {
+        self.velocity_tolerance = Some(velocity);
+        self
+    }
+
+    pub fn timeout(&mut self, duration: Duration) -> &mut Self {
+        self.timeout = Some(duration);
+        self
+    }
+}
+
+fn to_meters(point: Vec2<Length>) -> Vec2<f64> {
+    Vec2::new(point.x.get::<meter>(), point.y.get::<meter>())
+}
+
+// The curvature of the arc from the robot to the lookahead point, positive when
+// it bends left.
+fn curvature(position: Vec2<f64>, heading: f64, lookahead_point: Vec2<f64>) -> f64 {
+    // lookahead point in the robot's frame, x forward and y left
+    let offset = (lookahead_point - position).rotated(-heading);
+    let chord = offset.length().max(f64::EPSILON);
+    2.0 * offset.y / (chord * chord)
+}
+
+// Searches forward from the current segment for where the lookahead circle
+// crosses the path, keeping the crossing furthest along the path.
+fn find_lookahead(
+    path: &[Waypoint],
+    start: usize,
+    position: Vec2<f64>,
+    lookahead: f64,
+) -> Option<(usize, Vec2<f64>)> {
+    let mut found = None;
+
+    for (index, pair) in path.windows(2).enumerate().skip(start) {
+        let from = to_meters(pair[0].point);
+        let to = to_meters(pair[1].point);
+
+        let direction = to - from;
+        let relative = from - position;
+
+        // a segment starting outside the circle can only re-enter it further along
+        if found.is_some() && relative.length() > lookahead {
+            break;
+        }
+
+        let a = direction.dot(direction);
+        let b = 2.0 * relative.dot(direction);
+        let c = relative.dot(relative) - lookahead * lookahead;
+        let discriminant = b * b - 4.0 * a * c;
+
+        if a == 0.0 || discriminant < 0.0 {
+            continue;
+        }
+
+        let root = discriminant.sqrt();
+        let t1 = (-b - root) / (2.0 * a);
+        let t2 = (-b + root) / (2.0 * a);
+
+        for t in [t1, t2] {
+            if (0.0..=1.0).contains(&t) {
+                found = Some((index, from + direction * t));
+            }
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use uom::si::{f64::Length, length::meter};
+
+    use super::{Waypoint, curvature, find_lookahead};
+    use crate::localization::vec2::Vec2;
+
+    fn path(points: &[(f64, f64)]) -> Vec<Waypoint> {
+        points
+            .iter()
+            .map(|&(x, y)| {
+                Waypoint::from(Vec2::new(Length::new::<meter>(x), Length::new::<meter>(y)))
+            })
+            .collect()
+    }
+
+    fn assert_point(actual: Vec2<f64>, x: f64, y: f64) {
+        assert!(
+            (actual.x - x).abs() < 1e-9 && (actual.y - y).abs() < 1e-9,
+            "expected ({x}, {y}), got ({}, {})",
+            actual.x,
+            actual.y
+        );
+    }
+
+    #[test]
+    fn lookahead_is_where_the_circle_crosses_the_path() {
+        let path = path(&[(0.0, 0.0), (2.0, 0.0)]);
+
+        let (segment, point) = find_lookahead(&path, 0, Vec2::new(0.5, 0.3), 0.5).unwrap();
+
+        assert_eq!(segment, 0);
+        // the crossing further along the segment wins
+        assert_point(point, 0.9, 0.0);
+    }
+
+    #[test]
+    fn lookahead_moves_onto_later_segments() {
+        let path = path(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]);
+
+        let (segment, point) = find_lookahead(&path, 0, Vec2::new(0.8, 0.0), 0.5).unwrap();
+
+        assert_eq!(segment, 1);
+        assert_point(point, 1.0, 0.21f64.sqrt());
+    }
+
+    #[test]
+    fn no_lookahead_when_the_path_is_out_of_reach() {
+        let path = path(&[(0.0, 0.0), (2.0, 0.0)]);
+
+        assert!(find_lookahead(&path, 0, Vec2::new(1.0, 1.0), 0.5).is_none());
+    }
+
+    #[test]
+    fn curvature_bends_toward_the_lookahead_point() {
+        let origin = Vec2::new(0.0, 0.0);
+
+        assert!(curvature(origin, 0.0, Vec2::new(1.0, 0.5)) > 0.0);
+        assert!(curvature(origin, 0.0, Vec2::new(1.0, -0.5)) < 0.0);
+        assert!(curvature(origin, 0.0, Vec2::new(1.0, 0.0)).abs() < 1e-12);
+        // facing up the y axis, a point off to +x is on the right
+        assert!(curvature(origin, FRAC_PI_2, Vec2::new(0.5, 1.0)) < 0.0);
+    }
+
+    #[test]
+    fn curvature_matches_the_arc_through_the_point() {
+        // a quarter of the unit circle from (0, 0) to (1, 1) starting along x
+        let curvature = curvature(Vec2::new(0.0, 0.0), 0.0, Vec2::new(1.0, 1.0));
+
+        assert!((curvature - 1.0).abs() < 1e-9, "{curvature}");
+    }
+}
@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use uom::si::{
+    angle::radian, angular_velocity::radian_per_second, f64::Length, length::meter,
+    velocity::meter_per_second,
+};
+
+use crate::{
+    hardware::device::MotorDevice,
+    localization::{localizer::Localizer, pose::Pose},
+    motion::trajectory::Trajectory,
+    runtime::{Instant, sleep},
+    subsystems::drivetrain::Drivetrain,
+    utils::wrap,
+};
+
+/// Nonlinear trajectory tracker. `b` acts like a proportional gain on the pose
+/// error and `zeta` like damping, both must be positive and `zeta` below one.
+pub struct Ramsete {
+    b: f64,
+    zeta: f64,
+    tolerance: Length,
+    settle_time: Duration,
+    timeout: Option<Duration>,
+}
+
+impl Ramsete {
+    const DEFAULT_SETTLE_TIME: Duration = Duration::from_millis(500);
+
+    pub fn new(b: f64, zeta: f64, tolerance: Length) -> Self {
+        Self {
+            b,
+            zeta,
+            tolerance,
+            settle_time: Self::DEFAULT_SETTLE_TIME,
+            timeout: None,
+        }
+    }
+
+    pub async fn follow<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+        trajectory: &Trajectory,
+    ) {
+        let start_time = Instant::now();
+        let duration = trajectory.duration();
+
+        loop {
+            sleep(Duration::from_millis(10)).await;
+            let time = start_time.elapsed();
+
+            let pose = dt.pose();
+            let reference = trajectory.sample(time);
+
+            let (error_x, error_y, _) = error(pose, reference);
+
+            if time >= duration && error_x.hypot(error_y) < self.tolerance.get::<meter>() {
+                info!("Trajectory complete in {}ms", time.as_millis());
+                break;
+            }
+
+            // the reference is at rest after the end, which leaves the
+            // controller no gain to close what error is left
+            if time >= duration + self.settle_time {
+                warn!("Trajectory ended off target at: ({})", pose);
+                break;
+            }
+
+            if self.timeout.is_some_and(|timeout| time > timeout) {
+                warn!("Trajectory interrupted at: ({})", pose);
+                break;
+            }
+
+            debug!("Reference: ({}), Position: ({})", reference, pose);
+
+            let [left, right] = self.wheel_speeds(dt, reference);
+            dt.set_velocity(left, right);
+        }
+
+        self.timeout = None;
+
+        dt.set_voltages(0.0, 0.0);
+    }
+
+    pub fn timeout(&mut self, duration: Duration) -> &mut Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    /// How long after the trajectory ends the robot has to come within
+    /// `tolerance` of the end before giving up.
+    pub fn settle_time(&mut self, duration: Duration) -> &mut Self {
+        self.settle_time = duration;
+        self
+    }
+
+    /// The left and right wheel speeds in RPM that bring the robot onto
+    /// `reference` from where it is now.
+    pub fn wheel_speeds<M: MotorDevice, L: Localizer>(
+        &self,
+        dt: &mut Drivetrain<M, L>,
+        reference: Pose,
+    ) -> [f64; 2] {
+        let (error_x, error_y, error_h) = error(dt.pose(), reference);
+
+        let velocity = reference.vf.get::<meter_per_second>();
+        let omega = reference.omega.get::<radian_per_second>();
+
+        let gain = 2.0 * self.zeta * (omega * omega + self.b * velocity * velocity).sqrt();
+        let sinc = if error_h.abs() < 1e-6 {
+            1.0
+        } else {
+            error_h.sin() / error_h
+        };
+
+        let linear = velocity * error_h.cos() + gain * error_x;
+        let angular = omega + gain * error_h + self.b * velocity * sinc * error_y;
+
+        let half_track = dt.track().get::<meter>() / 2.0;
+        let to_rpm = 60.0 / dt.wheel_circum().get::<meter>();
+
+        [
+            (linear - angular * half_track) * to_rpm,
+            (linear + angular * half_track) * to_rpm,
+        ]
+    }
+}
+
+// the reference's offset from the pose in the robot's frame
+fn error(pose: Pose, reference: Pose) -> (f64, f64, f64) {
+    let dx = (reference.x - pose.x).get::<meter>();
+    let dy = (reference.y - pose.y).get::<meter>();
+    let (sin, cos) = pose.h.get::<radian>().sin_cos();
+
+    (
+        cos * dx + sin * dy,
+        -sin * dx + cos * dy,
+        wrap(reference.h - pose.h).get::<radian>(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uom::si::{
+        acceleration::meter_per_second_squared,
+        angle::radian,
+        f64::{Acceleration, Angle, Length, Velocity},
+        length::{inch, meter},
+        velocity::meter_per_second,
+    };
+
+    use super::Ramsete;
+    use crate::{
+        localization::{pose::Pose, vec2::Vec2},
+        motion::{
+            path::{PathGenerator, Spline},
+            trajectory::Trajectory,
+        },
+        simulation::tests::simulated_drivetrain,
+    };
+
+    fn trajectory() -> Trajectory {
+        let points = [(0.0, 0.0), (0.6, 0.0), (1.0, 0.4)]
+            .map(|(x, y)| Vec2::new(Length::new::<meter>(x), Length::new::<meter>(y)));
+
+        PathGenerator::new(
+            Length::new::<inch>(1.0),
+            Velocity::new::<meter_per_second>(1.0),
+            Acceleration::new::<meter_per_second_squared>(2.0),
+            Length::new::<inch>(12.0),
+        )
+        .generate(Spline::CatmullRom, &points)
+    }
+
+    fn start(y: f64) -> Pose {
+        Pose::new(
+            Length::new::<meter>(0.0),
+            Length::new::<meter>(y),
+            Angle::new::<radian>(0.0),
+        )
+    }
+
+    #[test]
+    fn follows_a_trajectory_to_its_end() {
+        let trajectory = trajectory();
+        let end = trajectory.sample(trajectory.duration());
+        let (mut sim, mut dt) = simulated_drivetrain(start(0.0));
+
+        let mut ramsete = Ramsete::new(2.0, 0.7, Length::new::<inch>(2.0));
+        let finished = sim.run(
+            ramsete.follow(&mut dt, &trajectory),
+            trajectory.duration() + Duration::from_secs(5),
+        );
+
+        assert!(finished.is_some(), "the trajectory never ended");
+        // finished inside the tolerance rather than running out the settle time
+        assert!(
+            sim.elapsed() < trajectory.duration() + Duration::from_millis(250),
+            "{:?}",
+            sim.elapsed()
+        );
+        let pose = sim.pose();
+        let miss = (pose.x - end.x).hypot(pose.y - end.y);
+        assert!(miss.get::<inch>() < 2.5, "ended {:?} from the end", miss);
+    }
+
+    #[test]
+    fn stops_after_settling_without_a_timeout() {
+        let trajectory = trajectory();
+        let (mut sim, mut dt) = simulated_drivetrain(start(0.05));
+
+        // tighter than the robot can get, so only the settle time ends it
+        let mut ramsete = Ramsete::new(2.0, 0.7, Length::new::<meter>(1e-6));
+        ramsete.settle_time(Duration::from_millis(300));
+        let finished = sim.run(
+            ramsete.follow(&mut dt, &trajectory),
+            trajectory.duration() + Duration::from_secs(5),
+        );
+
+        assert!(finished.is_some(), "the trajectory never ended");
+        let overtime = sim.elapsed() - trajectory.duration();
+        assert!(overtime <= Duration::from_millis(350), "{:?}", overtime);
+    }
+}
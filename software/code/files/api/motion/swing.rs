@@ -1,4 +1,4 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use log::{info, warn};
 use uom::si::{
@@ -6,35 +6,55 @@ use uom::si::{
     f64::{Angle, AngularVelocity, Length},
     length::meter,
 };
-use vexide::{prelude::Gearset, time::sleep};
+use vexide::prelude::Gearset;
 
 use crate::{
-    controllers::pid::Pid, motion::desaturate, subsystems::drivetrain::Drivetrain, utils::wrap,
+    controllers::{FeedbackController, pid::Pid},
+    hardware::device::MotorDevice,
+    localization::{localizer::Localizer, mirror::Mirror},
+    motion::desaturate,
+    runtime::{Instant, sleep},
+    subsystems::drivetrain::Drivetrain,
+    utils::wrap,
 };
 
-pub struct Swing {
-    pid: Pid,
+pub struct Swing<C: FeedbackController = Pid> {
+    controller: C,
     tolerance: Angle,
     velocity_tolerance: Option<AngularVelocity>,
     timeout: Option<Duration>,
+    mirror: Option<Mirror>,
 }
 
-impl Swing {
-    pub fn new(pid: Pid, tolerance: Angle) -> Self {
+impl<C: FeedbackController> Swing<C> {
+    pub fn new(controller: C, tolerance: Angle) -> Self {
         Self {
-            pid,
+            controller,
             tolerance,
             velocity_tolerance: None,
             timeout: None,
+            mirror: None,
         }
     }
 
-    pub async fn swing_to(&mut self, dt: &mut Drivetrain, target: Angle, radius: Length) {
+    pub async fn swing_to<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+        target: Angle,
+        radius: Length,
+    ) {
+        let (target, radius) = match &self.mirror {
+            Some(mirror) => (mirror.heading(target), mirror.turn(radius)),
+            None => (target, radius),
+        };
         let mut time = Duration::ZERO;
         let mut prev_time = Instant::now();
 
         let starting_error = wrap(target - dt.pose().h).abs();
+        // progress from the starting heading, like `Turn`
+        let total = wrap(target - dt.pose().h).get::<radian>();
         let length = dt.track();
+        self.controller.reset();
 
         loop {
             sleep(Duration::from_millis(10)).await;
@@ -44,7 +64,9 @@ impl Swing {
 
             let heading = dt.pose().h;
             let error = wrap(target - heading);
-            let output = self.pid.output(error.get::<radian>(), elapsed_time);
+            let output = self
+                .controller
+                .update(total, total - error.get::<radian>(), elapsed_time);
             let omega = dt.pose().omega;
 
             if error.abs() < self.tolerance
@@ -91,4 +113,15 @@ impl Swing {
         self.timeout = Some(duration);
         self
     }
+
+    /// Reflects every following target onto the robot's alliance side.
+    pub fn mirrored(&mut self, mirror: Mirror) -> &mut Self {
+        self.mirror = Some(mirror);
+        self
+    }
+
+    pub fn unmirrored(&mut self) -> &mut Self {
+        self.mirror = None;
+        self
+    }
 }
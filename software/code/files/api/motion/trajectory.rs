@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use uom::{ConstZero, si::f64::Velocity};
+
+use crate::{
+    localization::{pose::Pose, vec2::Vec2},
+    motion::pure_pursuit::Waypoint,
+};
+
+/// A point the robot should be at a given time since the start of the
+/// trajectory. The pose's `vf` and `omega` hold the reference velocities.
+#[derive(Clone, Copy)]
+pub struct TrajectoryPoint {
+    pub time: Duration,
+    pub pose: Pose,
+}
+
+#[derive(Clone, Default)]
+pub struct Trajectory {
+    points: Vec<TrajectoryPoint>,
+}
+
+impl Trajectory {
+    /// Points must be sorted by time.
+    pub fn new(points: Vec<TrajectoryPoint>) -> Self {
+        Self { points }
+    }
+
+    pub fn points(&self) -> &[TrajectoryPoint] {
+        &self.points
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.points
+            .last()
+            .map(|point| point.time)
+            .unwrap_or_default()
+    }
+
+    /// The reference pose at the given time, interpolated between points. Holds
+    /// the final pose at rest once the trajectory is over.
+    pub fn sample(&self, time: Duration) -> Pose {
+        let Some(last) = self.points.last() else {
+            return Pose::default();
+        };
+
+        if time >= last.time {
+            return Pose::new(last.pose.x, last.pose.y, last.pose.h);
+        }
+
+        let next = self.points.partition_point(|point| point.time <= time);
+        if next == 0 {
+            return self.points[0].pose;
+        }
+
+        let from = self.points[next - 1];
+        let to = self.points[next];
+        let span = (to.time - from.time).as_secs_f64();
+        let t = if span > 0.0 {
+            (time - from.time).as_secs_f64() / span
+        } else {
+            1.0
+        };
+
+        from.pose.interpolate(to.pose, t)
+    }
+
+    /// The trajectory's path for followers that only need positions, with each
+    /// point keeping its reference speed.
+    pub fn waypoints(&self) -> Vec<Waypoint> {
+        self.points
+            .iter()
+            .map(|point| {
+                let speed = point.pose.vf.abs();
+                Waypoint::new(
+                    Vec2::new(point.pose.x, point.pose.y),
+                    (speed > Velocity::ZERO).then_some(speed),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uom::si::{
+        angle::radian,
+        f64::{Angle, Length, Velocity},
+        length::meter,
+        velocity::meter_per_second,
+    };
+
+    use super::{Trajectory, TrajectoryPoint};
+    use crate::localization::pose::Pose;
+
+    fn point(millis: u64, x: f64, speed: f64) -> TrajectoryPoint {
+        let mut pose = Pose::new(
+            Length::new::<meter>(x),
+            Length::new::<meter>(0.0),
+            Angle::new::<radian>(0.0),
+        );
+        pose.vf = Velocity::new::<meter_per_second>(speed);
+
+        TrajectoryPoint {
+            time: Duration::from_millis(millis),
+            pose,
+        }
+    }
+
+    fn trajectory() -> Trajectory {
+        Trajectory::new(vec![
+            point(0, 0.0, 0.0),
+            point(1000, 0.5, 1.0),
+            point(2000, 1.0, 0.0),
+        ])
+    }
+
+    #[test]
+    fn samples_between_points_are_interpolated() {
+        let pose = trajectory().sample(Duration::from_millis(500));
+
+        assert!((pose.x.get::<meter>() - 0.25).abs() < 1e-9, "{:?}", pose.x);
+        assert!(
+            (pose.vf.get::<meter_per_second>() - 0.5).abs() < 1e-9,
+            "{:?}",
+            pose.vf
+        );
+    }
+
+    #[test]
+    fn end_is_held_at_rest() {
+        let trajectory = trajectory();
+        assert_eq!(trajectory.duration(), Duration::from_secs(2));
+
+        let pose = trajectory.sample(Duration::from_secs(5));
+        assert_eq!(pose.x.get::<meter>(), 1.0);
+        assert_eq!(pose.vf.get::<meter_per_second>(), 0.0);
+    }
+
+    #[test]
+    fn empty_trajectory_has_no_duration() {
+        let trajectory = Trajectory::default();
+
+        assert_eq!(trajectory.duration(), Duration::ZERO);
+        assert_eq!(
+            trajectory.sample(Duration::from_secs(1)).x.get::<meter>(),
+            0.0
+        );
+    }
+}
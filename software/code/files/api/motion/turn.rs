@@ -1,4 +1,4 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use log::{debug, info, warn};
 use uom::si::{
@@ -6,49 +6,84 @@ use uom::si::{
     angular_velocity::degree_per_second,
     f64::{Angle, AngularVelocity, Length},
 };
-use vexide::time::sleep;
 
 use crate::{
-    controllers::pid::Pid,
-    localization::vec2::Vec2,
+    controllers::{FeedbackController, feedforward::Feedforward, pid::Pid},
+    hardware::device::MotorDevice,
+    localization::{localizer::Localizer, mirror::Mirror, vec2::Vec2},
+    motion::profile::{Constraints, MotionProfile},
+    runtime::{Instant, sleep},
     subsystems::drivetrain::Drivetrain,
     utils::{angular_distance, wrap},
 };
 
-pub struct Turn {
-    pid: Pid,
+pub struct Turn<C: FeedbackController = Pid> {
+    controller: C,
     tolerance: Angle,
     velocity_tolerance: Option<AngularVelocity>,
     timeout: Option<Duration>,
     tolerance_scale: f64,
+    profile: Option<(Constraints, Feedforward)>,
+    mirror: Option<Mirror>,
 }
 
-impl Turn {
-    pub fn new(pid: Pid, tolerance: Angle) -> Self {
+impl<C: FeedbackController> Turn<C> {
+    pub fn new(controller: C, tolerance: Angle) -> Self {
         Self {
-            pid,
+            controller,
             tolerance,
             velocity_tolerance: None,
             timeout: None,
             tolerance_scale: 1.0,
+            profile: None,
+            mirror: None,
         }
     }
 
-    pub async fn turn_to_point(
+    pub async fn turn_to_point<M: MotorDevice, L: Localizer>(
         &mut self,
-        dt: &mut Drivetrain,
+        dt: &mut Drivetrain<M, L>,
         point: Vec2<Length>,
     ) {
         let pose = dt.pose();
+        let point = self
+            .mirror
+            .as_ref()
+            .map_or(point, |mirror| mirror.point(point));
         let target = angular_distance(pose, point);
-        self.turn_to(dt, target).await;
+        self.turn(dt, target).await;
     }
 
-    pub async fn turn_to(&mut self, dt: &mut Drivetrain, target: Angle) {
+    pub async fn turn_to<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+        target: Angle,
+    ) {
+        let target = self
+            .mirror
+            .as_ref()
+            .map_or(target, |mirror| mirror.heading(target));
+        self.turn(dt, target).await;
+    }
+
+    // the target is already on the robot's side of the field
+    async fn turn<M: MotorDevice, L: Localizer>(
+        &mut self,
+        dt: &mut Drivetrain<M, L>,
+        target: Angle,
+    ) {
         let mut time = Duration::ZERO;
         let mut prev_time = Instant::now();
 
-        let starting_error = wrap(target - dt.pose().h).abs();
+        let starting_heading = dt.pose().h;
+        let starting_error = wrap(target - starting_heading).abs();
+        // the turn is measured as progress from the starting heading so the
+        // controller sees a setpoint and measurement instead of a wrapped error
+        let total = wrap(target - starting_heading).get::<radian>();
+        let profile = self.profile.map(|(constraints, feedforward)| {
+            (MotionProfile::new(total, constraints), feedforward)
+        });
+        self.controller.reset();
 
         loop {
             sleep(Duration::from_millis(10)).await;
@@ -58,15 +93,31 @@ impl Turn {
 
             let heading = dt.pose().h;
             let error = wrap(target - heading);
-            let output = self.pid.output(error.get::<radian>(), elapsed_time);
             let omega = dt.pose().omega;
 
+            // a profiled turn follows the profile's setpoint instead of the target
+            let progress = total - error.get::<radian>();
+
+            let (setpoint, feedforward, profile_done) = match &profile {
+                Some((profile, feedforward)) => {
+                    let setpoint = profile.sample(time);
+                    (
+                        setpoint.position,
+                        feedforward.output(setpoint.velocity, setpoint.acceleration),
+                        time >= profile.duration(),
+                    )
+                }
+                None => (total, 0.0, true),
+            };
+            let output = feedforward + self.controller.update(setpoint, progress, elapsed_time);
+
             debug!(
                 "(Error, Velocity): ({}, {})",
                 error.get::<degree>(),
                 omega.get::<degree_per_second>()
             );
-            if error.abs() < self.tolerance * self.tolerance_scale
+            if profile_done
+                && error.abs() < self.tolerance * self.tolerance_scale
                 && self
                     .velocity_tolerance
                     .is_none_or(|tolerance| omega.abs() < tolerance)
@@ -112,4 +163,27 @@ impl Turn {
         self.tolerance_scale = scale;
         self
     }
+
+    /// Follows a motion profile on every following turn, with the feedforward
+    /// in volts per radian per second.
+    pub fn profiled(&mut self, constraints: Constraints, feedforward: Feedforward) -> &mut Self {
+        self.profile = Some((constraints, feedforward));
+        self
+    }
+
+    pub fn unprofiled(&mut self) -> &mut Self {
+        self.profile = None;
+        self
+    }
+
+    /// Reflects every following target onto the robot's alliance side.
+    pub fn mirrored(&mut self, mirror: Mirror) -> &mut Self {
+        self.mirror = Some(mirror);
+        self
+    }
+
+    pub fn unmirrored(&mut self) -> &mut Self {
+        self.mirror = None;
+        self
+    }
 }
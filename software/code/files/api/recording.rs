@@ -0,0 +1,514 @@
+use std::time::Duration;
+
+use bytemuck::{Pod, Zeroable};
+use log::{info, warn};
+use uom::si::{
+    angle::radian,
+    angular_velocity::radian_per_second,
+    f64::{Angle, AngularVelocity, Length, Velocity},
+    length::millimeter,
+    velocity::millimeter_per_second,
+};
+use vexide::{controller::ButtonState, io};
+
+use crate::{
+    hardware::device::MotorDevice,
+    localization::{localizer::Localizer, pose::Pose},
+    mappings::{ControllerMappings, DriveMode},
+    motion::{
+        ramsete::Ramsete,
+        trajectory::{Trajectory, TrajectoryPoint},
+    },
+    runtime::{Instant, sleep},
+    subsystems::drivetrain::Drivetrain,
+    utils::wrap,
+};
+
+/// The buttons in `ControllerMappings`, in the order they're stored.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Intake,
+    Outake,
+    Lift,
+    DuckBill,
+    Wing,
+    MatchLoad,
+    SwapColor,
+    EnableColor,
+}
+
+/// Which mapped buttons were held in a frame and in the one before it, so
+/// replayed presses can be told apart from holds like a live controller.
+#[derive(Clone, Copy, Default)]
+pub struct Buttons {
+    pressed: u16,
+    previous: u16,
+}
+
+impl Buttons {
+    pub fn from_mappings(mappings: &ControllerMappings) -> Self {
+        let states = [
+            (Button::Intake, mappings.intake),
+            (Button::Outake, mappings.outake),
+            (Button::Lift, mappings.lift),
+            (Button::DuckBill, mappings.duck_bill),
+            (Button::Wing, mappings.wing),
+            (Button::MatchLoad, mappings.match_load),
+            (Button::SwapColor, mappings.swap_color),
+            (Button::EnableColor, mappings.enable_color),
+        ];
+
+        let mut buttons = Self::default();
+        for (button, state) in states {
+            if state.is_pressed() {
+                buttons.pressed |= button.bit();
+            }
+            if was_pressed(state) {
+                buttons.previous |= button.bit();
+            }
+        }
+
+        buttons
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.pressed & button.bit() != 0
+    }
+
+    pub fn is_now_pressed(&self, button: Button) -> bool {
+        self.is_pressed(button) && self.previous & button.bit() == 0
+    }
+
+    pub fn is_now_released(&self, button: Button) -> bool {
+        !self.is_pressed(button) && self.previous & button.bit() != 0
+    }
+}
+
+impl Button {
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+fn was_pressed(state: ButtonState) -> bool {
+    if state.is_now_pressed() {
+        false
+    } else if state.is_now_released() {
+        true
+    } else {
+        state.is_pressed()
+    }
+}
+
+/// One sample of a driver controlled run.
+#[derive(Clone, Copy)]
+pub struct Frame {
+    /// Since the start of the recording.
+    pub time: Duration,
+    /// The two drive axes, forward and turn in arcade or left and right in
+    /// tank.
+    pub sticks: [f64; 2],
+    pub buttons: Buttons,
+    pub voltages: [f64; 2],
+    pub pose: Pose,
+}
+
+// The stored form of a frame, 22 bytes. Poses are kept in millimeters and
+// milliradians, which is finer than odometry can tell apart anyway.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct RawFrame {
+    time: u32,
+    sticks: [i8; 2],
+    buttons: u16,
+    voltages: [i16; 2],
+    x: i16,
+    y: i16,
+    h: i16,
+    vf: i16,
+    omega: i16,
+}
+
+/// Collects frames while the driver is in control. Call `record` once per
+/// driver loop after the drivetrain has been given its voltages.
+pub struct Recorder {
+    start: Instant,
+    frames: Vec<Frame>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn record<M: MotorDevice, L: Localizer>(
+        &mut self,
+        mappings: &ControllerMappings,
+        dt: &Drivetrain<M, L>,
+    ) {
+        let sticks = match &mappings.drive_mode {
+            DriveMode::Arcade { power, turn } => [power.y(), turn.x()],
+            DriveMode::Tank { left, right } => [left.y(), right.y()],
+        };
+
+        self.frames.push(Frame {
+            time: self.start.elapsed(),
+            sticks,
+            buttons: Buttons::from_mappings(mappings),
+            voltages: dt.voltages(),
+            pose: dt.pose(),
+        });
+    }
+
+    pub fn finish(self) -> Recording {
+        info!(
+            "Recorded {} frames over {}ms",
+            self.frames.len(),
+            self.frames.last().map_or(0, |frame| frame.time.as_millis())
+        );
+        Recording {
+            frames: self.frames,
+        }
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A recorded driver run that can be played back as an autonomous routine.
+#[derive(Clone)]
+pub struct Recording {
+    frames: Vec<Frame>,
+}
+
+impl Recording {
+    const MAGIC: &[u8; 4] = b"ATRC";
+    const VERSION: u8 = 1;
+    const HEADER_SIZE: usize = 5;
+    const FRAME_SIZE: usize = size_of::<RawFrame>();
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.frames
+            .last()
+            .map(|frame| frame.time)
+            .unwrap_or_default()
+    }
+
+    /// The recorded poses as a trajectory for a follower.
+    pub fn trajectory(&self) -> Trajectory {
+        Trajectory::new(
+            self.frames
+                .iter()
+                .map(|frame| TrajectoryPoint {
+                    time: frame.time,
+                    pose: frame.pose,
+                })
+                .collect(),
+        )
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(Self::HEADER_SIZE + self.frames.len() * Self::FRAME_SIZE);
+        bytes.extend_from_slice(Self::MAGIC);
+        bytes.push(Self::VERSION);
+
+        for frame in self.frames.iter() {
+            let raw = RawFrame {
+                time: frame.time.as_millis().min(u32::MAX as u128) as u32,
+                sticks: frame.sticks.map(|stick| quantize(stick * 127.0) as i8),
+                buttons: frame.buttons.pressed,
+                voltages: frame.voltages.map(|voltage| quantize(voltage * 1000.0)),
+                x: quantize(frame.pose.x.get::<millimeter>()),
+                y: quantize(frame.pose.y.get::<millimeter>()),
+                h: quantize(wrap(frame.pose.h).get::<radian>() * 1000.0),
+                vf: quantize(frame.pose.vf.get::<millimeter_per_second>()),
+                omega: quantize(frame.pose.omega.get::<radian_per_second>() * 1000.0),
+            };
+            bytes.extend_from_slice(bytemuck::bytes_of(&raw));
+        }
+
+        bytes
+    }
+
+    /// Reads a recording written by `to_bytes`, or `None` if it isn't one.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (header, body) = bytes.split_at_checked(Self::HEADER_SIZE)?;
+        if header[..4] != *Self::MAGIC || header[4] != Self::VERSION {
+            return None;
+        }
+        if body.len() % Self::FRAME_SIZE != 0 {
+            return None;
+        }
+
+        let mut previous = 0;
+        let frames = body
+            .chunks_exact(Self::FRAME_SIZE)
+            .map(|chunk| {
+                let raw: RawFrame = bytemuck::pod_read_unaligned(chunk);
+                let buttons = Buttons {
+                    pressed: raw.buttons,
+                    previous,
+                };
+                previous = raw.buttons;
+
+                let mut pose = Pose::new(
+                    Length::new::<millimeter>(raw.x as f64),
+                    Length::new::<millimeter>(raw.y as f64),
+                    Angle::new::<radian>(raw.h as f64 / 1000.0),
+                );
+                pose.vf = Velocity::new::<millimeter_per_second>(raw.vf as f64);
+                pose.omega = AngularVelocity::new::<radian_per_second>(raw.omega as f64 / 1000.0);
+
+                Frame {
+                    time: Duration::from_millis(raw.time as u64),
+                    sticks: raw.sticks.map(|stick| stick as f64 / 127.0),
+                    buttons,
+                    voltages: raw.voltages.map(|voltage| voltage as f64 / 1000.0),
+                    pose,
+                }
+            })
+            .collect();
+
+        Some(Self { frames })
+    }
+
+    /// Writes the recording to the SD card.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        vexide::fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: &str) -> Option<Self> {
+        let bytes = vexide::fs::read(path)
+            .inspect_err(|err| warn!("Couldn't read recording {}: {:?}", path, err))
+            .ok()?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Plays the recorded voltages back on their original schedule. Nothing
+    /// corrects for drift, so this is only as repeatable as the robot is.
+    /// `on_buttons` gets every frame's buttons to run the mechanisms with.
+    pub async fn replay_inputs<M: MotorDevice, L: Localizer>(
+        &self,
+        dt: &mut Drivetrain<M, L>,
+        mut on_buttons: impl FnMut(Buttons),
+    ) {
+        self.start(dt);
+        let start = Instant::now();
+        let mut next = 0;
+
+        while next < self.frames.len() {
+            let time = start.elapsed();
+            while let Some(frame) = self.frames.get(next).filter(|frame| frame.time <= time) {
+                on_buttons(frame.buttons);
+                dt.set_voltages(frame.voltages[0], frame.voltages[1]);
+                next += 1;
+            }
+
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        dt.set_voltages(0.0, 0.0);
+    }
+
+    /// Follows the recorded poses with `ramsete` so the run ends up where it
+    /// was driven even if the robot slips. Buttons are replayed on the
+    /// original schedule like `replay_inputs`.
+    pub async fn replay_poses<M: MotorDevice, L: Localizer>(
+        &self,
+        dt: &mut Drivetrain<M, L>,
+        ramsete: &Ramsete,
+        mut on_buttons: impl FnMut(Buttons),
+    ) {
+        self.start(dt);
+        let trajectory = self.trajectory();
+        let duration = trajectory.duration();
+        let start = Instant::now();
+        let mut next = 0;
+
+        loop {
+            sleep(Duration::from_millis(10)).await;
+            let time = start.elapsed();
+
+            while let Some(frame) = self.frames.get(next).filter(|frame| frame.time <= time) {
+                on_buttons(frame.buttons);
+                next += 1;
+            }
+
+            if time >= duration {
+                info!("Replay finished at ({})", dt.pose());
+                break;
+            }
+
+            let [left, right] = ramsete.wheel_speeds(dt, trajectory.sample(time));
+            dt.set_velocity(left, right);
+        }
+
+        dt.set_voltages(0.0, 0.0);
+    }
+
+    // both replays begin from where the recording did
+    fn start<M: MotorDevice, L: Localizer>(&self, dt: &mut Drivetrain<M, L>) {
+        match self.frames.first() {
+            Some(frame) => dt.set_pose(Pose::new(frame.pose.x, frame.pose.y, frame.pose.h)),
+            None => warn!("Replaying an empty recording"),
+        }
+    }
+}
+
+fn quantize(value: f64) -> i16 {
+    value.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uom::si::{
+        angle::radian,
+        angular_velocity::radian_per_second,
+        f64::{Angle, AngularVelocity, Length, Velocity},
+        length::{meter, millimeter},
+        velocity::millimeter_per_second,
+    };
+
+    use super::{Buttons, Frame, Recording};
+    use crate::localization::pose::Pose;
+
+    fn frame(millis: u64, pressed: u16, x: f64, h: f64, voltage: f64) -> Frame {
+        let mut pose = Pose::new(
+            Length::new::<millimeter>(x),
+            Length::new::<millimeter>(-x / 2.0),
+            Angle::new::<radian>(h),
+        );
+        pose.vf = Velocity::new::<millimeter_per_second>(812.3);
+        pose.omega = AngularVelocity::new::<radian_per_second>(-1.2345);
+
+        Frame {
+            time: Duration::from_millis(millis),
+            sticks: [0.5, -1.0],
+            buttons: Buttons {
+                pressed,
+                previous: 0,
+            },
+            voltages: [voltage, -voltage],
+            pose,
+        }
+    }
+
+    fn recording() -> Recording {
+        Recording {
+            frames: vec![
+                frame(0, 0b01, 1234.4, 0.1234, 11.9996),
+                frame(10, 0b11, -250.6, 3.0, 6.5),
+            ],
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64, step: f64) {
+        assert!(
+            (actual - expected).abs() <= step / 2.0 + 1e-9,
+            "expected {expected} to within {step}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn round_trip_keeps_frames_to_their_precision() {
+        let original = recording();
+        let read = Recording::from_bytes(&original.to_bytes()).unwrap();
+        assert_eq!(read.frames().len(), 2);
+
+        for (read, original) in read.frames().iter().zip(original.frames()) {
+            assert_eq!(read.time, original.time);
+            assert_eq!(read.buttons.pressed, original.buttons.pressed);
+            for (read, original) in read.sticks.iter().zip(original.sticks) {
+                assert_close(*read, original, 1.0 / 127.0);
+            }
+            for (read, original) in read.voltages.iter().zip(original.voltages) {
+                assert_close(*read, original, 0.001);
+            }
+
+            let (pose, expected) = (read.pose, original.pose);
+            assert_close(
+                pose.x.get::<millimeter>(),
+                expected.x.get::<millimeter>(),
+                1.0,
+            );
+            assert_close(
+                pose.y.get::<millimeter>(),
+                expected.y.get::<millimeter>(),
+                1.0,
+            );
+            assert_close(pose.h.get::<radian>(), expected.h.get::<radian>(), 0.001);
+            assert_close(
+                pose.vf.get::<millimeter_per_second>(),
+                expected.vf.get::<millimeter_per_second>(),
+                1.0,
+            );
+            assert_close(
+                pose.omega.get::<radian_per_second>(),
+                expected.omega.get::<radian_per_second>(),
+                0.001,
+            );
+        }
+    }
+
+    #[test]
+    fn previous_buttons_come_from_the_frame_before() {
+        let read = Recording::from_bytes(&recording().to_bytes()).unwrap();
+        let [first, second] = read.frames() else {
+            panic!("expected two frames");
+        };
+
+        assert_eq!(first.buttons.previous, 0);
+        assert_eq!(second.buttons.previous, first.buttons.pressed);
+    }
+
+    #[test]
+    fn values_past_the_stored_range_are_clamped() {
+        let mut recording = recording();
+        recording.frames[0].pose.x = Length::new::<meter>(40.0);
+        let read = Recording::from_bytes(&recording.to_bytes()).unwrap();
+
+        assert_close(
+            read.frames()[0].pose.x.get::<millimeter>(),
+            i16::MAX as f64,
+            1.0,
+        );
+    }
+
+    #[test]
+    fn wrong_magic_or_version_is_rejected() {
+        let bytes = recording().to_bytes();
+
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert!(Recording::from_bytes(&magic).is_none());
+
+        let mut version = bytes;
+        version[4] = Recording::VERSION + 1;
+        assert!(Recording::from_bytes(&version).is_none());
+    }
+
+    #[test]
+    fn truncated_bytes_are_rejected() {
+        let bytes = recording().to_bytes();
+
+        assert!(Recording::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        assert!(Recording::from_bytes(&bytes[..Recording::HEADER_SIZE - 1]).is_none());
+        assert!(Recording::from_bytes(&[]).is_none());
+
+        let empty = Recording::from_bytes(&bytes[..Recording::HEADER_SIZE]).unwrap();
+        assert!(empty.frames().is_empty());
+    }
+}
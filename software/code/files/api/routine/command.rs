@@ -0,0 +1,664 @@
+use std::{
+    fmt::{Display, Formatter},
+    iter::Peekable,
+    str::Chars,
+    time::Duration,
+};
+
+use uom::si::{
+    angle::degree,
+    f64::{Angle, Length},
+    length::inch,
+};
+
+use crate::localization::{pose::Pose, vec2::Vec2};
+
+// The V5 motors top out at 12 volts. Kept here instead of using vexide's
+// constant so that routines can be checked on a computer.
+const MAX_VOLTAGE: f64 = 12.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PistonState {
+    High,
+    Low,
+    Toggle,
+}
+
+/// One step of a routine. Distances are in inches, angles in degrees,
+/// durations in milliseconds and voltages in volts when written out.
+#[derive(Clone)]
+pub enum Command {
+    SetPose(Pose),
+    Drive {
+        distance: Length,
+        /// Fraction of full speed.
+        speed: Option<f64>,
+        timeout: Option<Duration>,
+    },
+    Turn {
+        heading: Angle,
+        timeout: Option<Duration>,
+    },
+    TurnToPoint {
+        point: Vec2<Length>,
+        timeout: Option<Duration>,
+    },
+    Swing {
+        heading: Angle,
+        radius: Length,
+        timeout: Option<Duration>,
+    },
+    MoveTo {
+        point: Vec2<Length>,
+        /// Arrives facing this heading when set, otherwise just reaches the
+        /// point.
+        heading: Option<Angle>,
+        timeout: Option<Duration>,
+    },
+    Intake {
+        voltage: f64,
+    },
+    Piston {
+        name: String,
+        state: PistonState,
+    },
+    Wait {
+        duration: Duration,
+    },
+}
+
+/// Where a routine failed to parse or validate, with the line counted from 1.
+#[derive(Debug)]
+pub struct RoutineError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// An autonomous routine written as a list of commands instead of Rust, so it
+/// reads as the steps of the route without the code that drives them:
+///
+/// ```text
+/// // starts facing the long goal
+/// SetPose(x: 84, y: 24, h: 55)
+/// Intake(voltage: 12)
+/// Drive(distance: 45, timeout: 1000)
+/// Turn(heading: 135, timeout: 1000)
+/// Swing(heading: -90, radius: -5)
+/// MoveTo(x: 24, y: 48, h: 90, timeout: 1500)
+/// Piston(name: brake, state: high)
+/// Wait(time: 2500)
+/// ```
+///
+/// Commands may be separated by commas and `//` starts a comment. Nothing in
+/// this file depends on the brain, so routines can be parsed and validated on
+/// a computer before they are uploaded, which `sim --check` does for a whole
+/// directory of them.
+#[derive(Clone)]
+pub struct Routine {
+    commands: Vec<(usize, Command)>,
+}
+
+impl Routine {
+    pub fn commands(&self) -> impl Iterator<Item = &Command> {
+        self.commands.iter().map(|(_, command)| command)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, RoutineError> {
+        let mut tokens = Tokens::new(source);
+        let mut commands = Vec::new();
+
+        while let Some((line, token)) = tokens.next()? {
+            let name = match token {
+                Token::Ident(name) => name,
+                // commands can be separated by commas like in a list
+                Token::Comma => continue,
+                token => {
+                    return Err(RoutineError::new(
+                        line,
+                        format!("expected a command, found {token}"),
+                    ));
+                }
+            };
+
+            let fields = Fields::parse(&mut tokens, line)?;
+            commands.push((line, Command::from_fields(&name, fields, line)?));
+        }
+
+        Ok(Self { commands })
+    }
+
+    /// Checks the routine against the pistons the robot actually has.
+    pub fn validate(&self, pistons: &[&str]) -> Result<(), RoutineError> {
+        for (line, command) in self.commands.iter() {
+            match command {
+                Command::Piston { name, .. } if !pistons.contains(&name.as_str()) => {
+                    return Err(RoutineError::new(*line, format!("unknown piston `{name}`")));
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Routine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for command in self.commands() {
+            writeln!(f, "{command}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Command {
+    fn from_fields(name: &str, mut fields: Fields, line: usize) -> Result<Self, RoutineError> {
+        let command = match name {
+            "SetPose" => Self::SetPose(Pose::new(
+                fields.length("x")?,
+                fields.length("y")?,
+                fields.angle("h")?,
+            )),
+            "Drive" => Self::Drive {
+                distance: fields.length("distance")?,
+                speed: fields
+                    .has("speed")
+                    .then(|| fields.ranged("speed", 0.0, 1.0))
+                    .transpose()?,
+                timeout: fields.timeout()?,
+            },
+            "Turn" => Self::Turn {
+                heading: fields.angle("heading")?,
+                timeout: fields.timeout()?,
+            },
+            "TurnToPoint" => Self::TurnToPoint {
+                point: Vec2::new(fields.length("x")?, fields.length("y")?),
+                timeout: fields.timeout()?,
+            },
+            "Swing" => Self::Swing {
+                heading: fields.angle("heading")?,
+                radius: fields.length("radius")?,
+                timeout: fields.timeout()?,
+            },
+            "MoveTo" => Self::MoveTo {
+                point: Vec2::new(fields.length("x")?, fields.length("y")?),
+                heading: fields.has("h").then(|| fields.angle("h")).transpose()?,
+                timeout: fields.timeout()?,
+            },
+            "Intake" => Self::Intake {
+                voltage: fields.ranged("voltage", -MAX_VOLTAGE, MAX_VOLTAGE)?,
+            },
+            "Piston" => Self::Piston {
+                name: fields.ident("name")?,
+                state: match fields.ident("state")?.as_str() {
+                    "high" => PistonState::High,
+                    "low" => PistonState::Low,
+                    "toggle" => PistonState::Toggle,
+                    state => {
+                        return Err(RoutineError::new(
+                            line,
+                            format!("`state` must be high, low or toggle, found `{state}`"),
+                        ));
+                    }
+                },
+            },
+            "Wait" => Self::Wait {
+                duration: fields.duration("time")?,
+            },
+            name => return Err(RoutineError::new(line, format!("unknown command `{name}`"))),
+        };
+
+        fields.finish()?;
+        Ok(command)
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let length = |length: Length| number(length.get::<inch>());
+        let angle = |angle: Angle| number(angle.get::<degree>());
+        let timeout = |timeout: &Option<Duration>| {
+            timeout.map_or(String::new(), |timeout| {
+                format!(", timeout: {}", timeout.as_millis())
+            })
+        };
+
+        match self {
+            Self::SetPose(pose) => write!(
+                f,
+                "SetPose(x: {}, y: {}, h: {})",
+                length(pose.x),
+                length(pose.y),
+                angle(pose.h)
+            ),
+            Self::Drive {
+                distance,
+                speed,
+                timeout: time,
+            } => write!(
+                f,
+                "Drive(distance: {}{}{})",
+                length(*distance),
+                speed.map_or(String::new(), |speed| format!(", speed: {}", number(speed))),
+                timeout(time)
+            ),
+            Self::Turn {
+                heading,
+                timeout: time,
+            } => write!(f, "Turn(heading: {}{})", angle(*heading), timeout(time)),
+            Self::TurnToPoint {
+                point,
+                timeout: time,
+            } => write!(
+                f,
+                "TurnToPoint(x: {}, y: {}{})",
+                length(point.x),
+                length(point.y),
+                timeout(time)
+            ),
+            Self::Swing {
+                heading,
+                radius,
+                timeout: time,
+            } => write!(
+                f,
+                "Swing(heading: {}, radius: {}{})",
+                angle(*heading),
+                length(*radius),
+                timeout(time)
+            ),
+            Self::MoveTo {
+                point,
+                heading,
+                timeout: time,
+            } => write!(
+                f,
+                "MoveTo(x: {}, y: {}{}{})",
+                length(point.x),
+                length(point.y),
+                heading.map_or(String::new(), |heading| format!(", h: {}", angle(heading))),
+                timeout(time)
+            ),
+            Self::Intake { voltage } => write!(f, "Intake(voltage: {})", number(*voltage)),
+            Self::Piston { name, state } => {
+                let state = match state {
+                    PistonState::High => "high",
+                    PistonState::Low => "low",
+                    PistonState::Toggle => "toggle",
+                };
+                write!(f, "Piston(name: {name}, state: {state})")
+            }
+            Self::Wait { duration } => write!(f, "Wait(time: {})", duration.as_millis()),
+        }
+    }
+}
+
+impl RoutineError {
+    fn new(line: usize, message: String) -> Self {
+        Self { line, message }
+    }
+}
+
+impl Display for RoutineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// unit conversions leave noise in the last few digits, which would show up
+// every time a routine is written back out
+fn number(value: f64) -> String {
+    let value = format!("{value:.3}");
+    value
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+enum Value {
+    Number(f64),
+    Ident(String),
+}
+
+// the named values inside a command's parentheses, taken out one at a time so
+// that anything left over is a typo
+struct Fields {
+    line: usize,
+    values: Vec<(String, Value)>,
+}
+
+impl Fields {
+    fn parse(tokens: &mut Tokens, line: usize) -> Result<Self, RoutineError> {
+        tokens.expect(Token::Open)?;
+        let mut values: Vec<(String, Value)> = Vec::new();
+
+        loop {
+            let (line, token) = tokens.expect_some()?;
+            let name = match token {
+                Token::Close => break,
+                Token::Ident(name) => name,
+                token => {
+                    return Err(RoutineError::new(
+                        line,
+                        format!("expected a field, found {token}"),
+                    ));
+                }
+            };
+            if values.iter().any(|(existing, _)| *existing == name) {
+                return Err(RoutineError::new(line, format!("`{name}` is set twice")));
+            }
+
+            tokens.expect(Token::Colon)?;
+            let value = match tokens.expect_some()? {
+                (_, Token::Number(number)) => Value::Number(number),
+                (_, Token::Ident(ident)) => Value::Ident(ident),
+                (line, token) => {
+                    return Err(RoutineError::new(
+                        line,
+                        format!("expected a value, found {token}"),
+                    ));
+                }
+            };
+            values.push((name, value));
+
+            match tokens.expect_some()? {
+                (_, Token::Comma) => continue,
+                (_, Token::Close) => break,
+                (line, token) => {
+                    return Err(RoutineError::new(
+                        line,
+                        format!("expected `,` or `)`, found {token}"),
+                    ));
+                }
+            }
+        }
+
+        Ok(Self { line, values })
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.values.iter().any(|(field, _)| field == name)
+    }
+
+    fn take(&mut self, name: &str) -> Result<Value, RoutineError> {
+        let index = self
+            .values
+            .iter()
+            .position(|(field, _)| field == name)
+            .ok_or_else(|| RoutineError::new(self.line, format!("missing `{name}`")))?;
+
+        Ok(self.values.remove(index).1)
+    }
+
+    fn number(&mut self, name: &str) -> Result<f64, RoutineError> {
+        match self.take(name)? {
+            Value::Number(number) => Ok(number),
+            Value::Ident(ident) => Err(RoutineError::new(
+                self.line,
+                format!("`{name}` must be a number, found `{ident}`"),
+            )),
+        }
+    }
+
+    fn ident(&mut self, name: &str) -> Result<String, RoutineError> {
+        match self.take(name)? {
+            Value::Ident(ident) => Ok(ident),
+            Value::Number(number) => Err(RoutineError::new(
+                self.line,
+                format!("`{name}` must be a name, found {number}"),
+            )),
+        }
+    }
+
+    fn ranged(&mut self, name: &str, min: f64, max: f64) -> Result<f64, RoutineError> {
+        let number = self.number(name)?;
+        if !(min..=max).contains(&number) {
+            return Err(RoutineError::new(
+                self.line,
+                format!("`{name}` must be between {min} and {max}, found {number}"),
+            ));
+        }
+
+        Ok(number)
+    }
+
+    fn length(&mut self, name: &str) -> Result<Length, RoutineError> {
+        self.number(name).map(Length::new::<inch>)
+    }
+
+    fn angle(&mut self, name: &str) -> Result<Angle, RoutineError> {
+        self.number(name).map(Angle::new::<degree>)
+    }
+
+    fn duration(&mut self, name: &str) -> Result<Duration, RoutineError> {
+        let millis = self.number(name)?;
+        if millis < 0.0 {
+            return Err(RoutineError::new(
+                self.line,
+                format!("`{name}` can't be negative, found {millis}"),
+            ));
+        }
+
+        Duration::try_from_secs_f64(millis / 1000.0).map_err(|_| {
+            RoutineError::new(self.line, format!("`{name}` is too long, found {millis}"))
+        })
+    }
+
+    fn timeout(&mut self) -> Result<Option<Duration>, RoutineError> {
+        self.has("timeout")
+            .then(|| self.duration("timeout"))
+            .transpose()
+    }
+
+    fn finish(self) -> Result<(), RoutineError> {
+        match self.values.first() {
+            Some((name, _)) => Err(RoutineError::new(
+                self.line,
+                format!("unknown field `{name}`"),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Open,
+    Close,
+    Colon,
+    Comma,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ident(ident) => write!(f, "`{ident}`"),
+            Self::Number(number) => write!(f, "{number}"),
+            Self::Open => write!(f, "`(`"),
+            Self::Close => write!(f, "`)`"),
+            Self::Colon => write!(f, "`:`"),
+            Self::Comma => write!(f, "`,`"),
+        }
+    }
+}
+
+struct Tokens<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            chars: source.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<(usize, Token)>, RoutineError> {
+        self.skip_blank();
+        let Some(&c) = self.chars.peek() else {
+            return Ok(None);
+        };
+
+        let token = match c {
+            '(' | ')' | ':' | ',' => {
+                self.chars.next();
+                match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    ':' => Token::Colon,
+                    _ => Token::Comma,
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                Token::Ident(self.take_while(|c| c.is_ascii_alphanumeric() || c == '_'))
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                let number = self.take_while(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+                match number.parse::<f64>() {
+                    Ok(value) if value.is_finite() => Token::Number(value),
+                    _ => {
+                        return Err(RoutineError::new(
+                            self.line,
+                            format!("`{number}` is not a number"),
+                        ));
+                    }
+                }
+            }
+            c => return Err(RoutineError::new(self.line, format!("unexpected `{c}`"))),
+        };
+
+        Ok(Some((self.line, token)))
+    }
+
+    fn expect_some(&mut self) -> Result<(usize, Token), RoutineError> {
+        self.next()?
+            .ok_or_else(|| RoutineError::new(self.line, "unexpected end of routine".to_string()))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), RoutineError> {
+        match self.expect_some()? {
+            (_, token) if token == expected => Ok(()),
+            (line, token) => Err(RoutineError::new(
+                line,
+                format!("expected {expected}, found {token}"),
+            )),
+        }
+    }
+
+    fn skip_blank(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c == '\n' {
+                self.line += 1;
+                self.chars.next();
+            } else if c.is_whitespace() {
+                self.chars.next();
+            } else if c == '/' && self.chars.clone().nth(1) == Some('/') {
+                // comments run to the end of the line, the newline is left to
+                // count it
+                while self.chars.next_if(|&c| c != '\n').is_some() {}
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let mut taken = String::new();
+        while let Some(c) = self.chars.next_if(|&c| predicate(c)) {
+            taken.push(c);
+        }
+        taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, Routine};
+
+    const PISTONS: [&str; 2] = ["brake", "wing"];
+
+    fn error_line(source: &str) -> usize {
+        match Routine::parse(source) {
+            Ok(_) => panic!("parsed {source:?}"),
+            Err(err) => err.line,
+        }
+    }
+
+    #[test]
+    fn documented_example_is_valid() {
+        let source = "
+            // starts facing the long goal
+            SetPose(x: 84, y: 24, h: 55)
+            Intake(voltage: 12)
+            Drive(distance: 45, timeout: 1000)
+            Turn(heading: 135, timeout: 1000)
+            Swing(heading: -90, radius: -5)
+            MoveTo(x: 24, y: 48, h: 90, timeout: 1500)
+            Piston(name: brake, state: high)
+            Wait(time: 2500)
+        ";
+        let routine = Routine::parse(source).unwrap();
+
+        assert_eq!(routine.commands().count(), 8);
+        assert!(routine.validate(&PISTONS).is_ok());
+    }
+
+    #[test]
+    fn errors_point_at_their_line() {
+        assert_eq!(error_line("Drive(distance: 24)\n\nJump(height: 2)"), 3);
+        assert_eq!(error_line("Wait(time: 10)\nDrive(distance: far)"), 2);
+        assert_eq!(error_line("Intake(voltage: 12)\nIntake(voltage: 20)"), 2);
+        assert_eq!(error_line("Drive(distance: 1, distance: 2)"), 1);
+        assert_eq!(
+            error_line("Turn(heading: 90)\nTurn(heading: 90, speed: 1)"),
+            2
+        );
+        assert_eq!(error_line("// comment\nTurn(heading: 90"), 2);
+        assert_eq!(error_line("Wait(time: 10)\nWait(time: 1e300)"), 2);
+        assert_eq!(error_line("Drive(distance: 24, timeout: 1e300)"), 1);
+    }
+
+    #[test]
+    fn unknown_pistons_fail_validation() {
+        let routine = Routine::parse("Wait(time: 10)\nPiston(name: claw, state: toggle)").unwrap();
+        let err = routine.validate(&PISTONS).unwrap_err();
+
+        assert_eq!(err.line, 2);
+        assert_eq!(err.to_string(), "line 2: unknown piston `claw`");
+    }
+
+    #[test]
+    fn written_routines_parse_back_the_same() {
+        let source = "
+            SetPose(x: 84, y: 24, h: 55)
+            Drive(distance: -20.5, speed: 0.5, timeout: 1000)
+            TurnToPoint(x: 10, y: -10)
+            Swing(heading: -90, radius: -5, timeout: 800)
+            MoveTo(x: 24, y: 48)
+            MoveTo(x: 24, y: 48, h: 90, timeout: 1500)
+            Intake(voltage: -12)
+            Piston(name: wing, state: toggle)
+            Wait(time: 250)
+        ";
+        let written = Routine::parse(source).unwrap().to_string();
+        let rewritten = Routine::parse(&written).unwrap().to_string();
+
+        assert_eq!(written, rewritten);
+        assert_eq!(written.lines().count(), 9);
+        assert!(written.contains("Drive(distance: -20.5, speed: 0.5, timeout: 1000)"));
+    }
+
+    #[test]
+    fn units_convert_on_the_way_in() {
+        let routine = Routine::parse("Wait(time: 1500)").unwrap();
+
+        match routine.commands().next() {
+            Some(Command::Wait { duration }) => assert_eq!(duration.as_millis(), 1500),
+            _ => panic!("expected a wait"),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use log::{info, warn};
+
+use super::command::{Command, PistonState, Routine};
+use crate::{
+    controllers::{FeedbackController, pid::Pid},
+    hardware::device::{DigitalOutDevice, MotorDevice},
+    localization::{localizer::Localizer, pose::Pose},
+    motion::{linear::Linear, move_to::MoveTo, swing::Swing, turn::Turn},
+    runtime::sleep,
+    subsystems::{drivetrain::Drivetrain, intake::Intake},
+};
+
+/// A piston a routine can refer to by name.
+pub type Piston<'a> = (&'a str, &'a mut dyn DigitalOutDevice);
+
+/// Runs routines with one set of tuned motions, so the gains live in a single
+/// place instead of being rebuilt at the top of every auton. Mirroring and
+/// profiles set on the motions carry over to every command.
+pub struct Interpreter<
+    L: FeedbackController = Pid,
+    T: FeedbackController = Pid,
+    S: FeedbackController = Pid,
+    D: FeedbackController = Pid,
+> {
+    linear: Linear<L>,
+    turn: Turn<T>,
+    swing: Swing<S>,
+    move_to: MoveTo<D>,
+}
+
+impl<L: FeedbackController, T: FeedbackController, S: FeedbackController, D: FeedbackController>
+    Interpreter<L, T, S, D>
+{
+    pub fn new(linear: Linear<L>, turn: Turn<T>, swing: Swing<S>, move_to: MoveTo<D>) -> Self {
+        Self {
+            linear,
+            turn,
+            swing,
+            move_to,
+        }
+    }
+
+    /// Runs every command in order. Pistons the robot doesn't have are skipped
+    /// with a warning, `Routine::validate` catches them ahead of time.
+    pub async fn run<M: MotorDevice, Loc: Localizer>(
+        &mut self,
+        routine: &Routine,
+        dt: &mut Drivetrain<M, Loc>,
+        intake: &Intake,
+        pistons: &mut [Piston<'_>],
+    ) {
+        for command in routine.commands() {
+            info!("Running {}", command);
+
+            match command {
+                Command::SetPose(pose) => dt.set_pose(*pose),
+                Command::Drive {
+                    distance,
+                    speed,
+                    timeout,
+                } => {
+                    if let Some(speed) = speed {
+                        self.linear.speed(*speed);
+                    }
+                    if let Some(timeout) = timeout {
+                        self.linear.timeout(*timeout);
+                    }
+                    self.linear.drive_distance(dt, *distance).await;
+                }
+                Command::Turn { heading, timeout } => {
+                    if let Some(timeout) = timeout {
+                        self.turn.timeout(*timeout);
+                    }
+                    self.turn.turn_to(dt, *heading).await;
+                }
+                Command::TurnToPoint { point, timeout } => {
+                    if let Some(timeout) = timeout {
+                        self.turn.timeout(*timeout);
+                    }
+                    self.turn.turn_to_point(dt, *point).await;
+                }
+                Command::Swing {
+                    heading,
+                    radius,
+                    timeout,
+                } => {
+                    if let Some(timeout) = timeout {
+                        self.swing.timeout(*timeout);
+                    }
+                    self.swing.swing_to(dt, *heading, *radius).await;
+                }
+                Command::MoveTo {
+                    point,
+                    heading,
+                    timeout,
+                } => {
+                    if let Some(timeout) = timeout {
+                        self.move_to.timeout(*timeout);
+                    }
+                    match heading {
+                        Some(heading) => {
+                            let target = Pose::new(point.x, point.y, *heading);
+                            self.move_to.move_to_pose(dt, target).await;
+                        }
+                        None => self.move_to.move_to_point(dt, *point).await,
+                    }
+                }
+                Command::Intake { voltage } => {
+                    intake.set_voltage(*voltage);
+                }
+                Command::Piston { name, state } => {
+                    let Some((_, piston)) = pistons
+                        .iter_mut()
+                        .find(|(piston, _)| *piston == name.as_str())
+                    else {
+                        warn!("Skipping unknown piston `{}`", name);
+                        continue;
+                    };
+
+                    match state {
+                        PistonState::High => piston.set_high(),
+                        PistonState::Low => piston.set_low(),
+                        PistonState::Toggle => piston.toggle(),
+                    }
+                }
+                Command::Wait { duration } => sleep(*duration).await,
+            }
+        }
+    }
+}
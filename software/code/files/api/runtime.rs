@@ -0,0 +1,170 @@
+use std::{
+    cell::RefCell,
+    future::{Future, poll_fn},
+    ops::{Add, AddAssign, Sub},
+    pin::Pin,
+    rc::{Rc, Weak},
+    sync::OnceLock,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+// Time, sleeping and background tasks for the rest of the API. On the robot
+// these are the brain's clock and vexide's executor. While a `Simulation` is
+// running they come from the simulation instead, so time only moves when it
+// steps and a run plays out the same way every time.
+
+type SimulatedTask = RefCell<Option<Pin<Box<dyn Future<Output = ()>>>>>;
+
+struct SimulatedRuntime {
+    now: Duration,
+    tasks: Vec<Weak<SimulatedTask>>,
+}
+
+thread_local! {
+    static SIMULATED: RefCell<Option<SimulatedRuntime>> = const { RefCell::new(None) };
+}
+
+/// A point in time that works like `std::time::Instant`, but follows the
+/// simulation's clock while one is running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Duration);
+
+impl Instant {
+    pub fn now() -> Self {
+        let simulated =
+            SIMULATED.with_borrow(|runtime| runtime.as_ref().map(|runtime| runtime.now));
+
+        Self(simulated.unwrap_or_else(|| {
+            static START: OnceLock<std::time::Instant> = OnceLock::new();
+            START.get_or_init(std::time::Instant::now).elapsed()
+        }))
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Self::now() - *self
+    }
+
+    /// Zero if `earlier` is actually later.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0 + duration)
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        self.0 += duration;
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+pub async fn sleep(duration: Duration) {
+    if !is_simulated() {
+        vexide::time::sleep(duration).await;
+        return;
+    }
+
+    // always yields at least once so a loop sleeping for zero still lets the
+    // simulation step
+    let deadline = Instant::now() + duration;
+    let mut yielded = false;
+    poll_fn(|_| {
+        if yielded && Instant::now() >= deadline {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            Poll::Pending
+        }
+    })
+    .await;
+}
+
+/// A background task, cancelled when it's dropped.
+pub struct Task {
+    _handle: TaskHandle,
+}
+
+enum TaskHandle {
+    Robot { _task: vexide::task::Task<()> },
+    Simulated { _task: Rc<SimulatedTask> },
+}
+
+pub fn spawn(future: impl Future<Output = ()> + 'static) -> Task {
+    let handle = SIMULATED.with_borrow_mut(|runtime| match runtime {
+        Some(runtime) => {
+            let task: Rc<SimulatedTask> = Rc::new(RefCell::new(Some(Box::pin(future))));
+            runtime.tasks.push(Rc::downgrade(&task));
+            TaskHandle::Simulated { _task: task }
+        }
+        None => TaskHandle::Robot {
+            _task: vexide::task::spawn(future),
+        },
+    });
+
+    Task { _handle: handle }
+}
+
+pub(crate) fn is_simulated() -> bool {
+    SIMULATED.with_borrow(|runtime| runtime.is_some())
+}
+
+/// Switches this thread over to simulated time, starting from zero.
+pub(crate) fn start_simulation() {
+    SIMULATED.with_borrow_mut(|runtime| {
+        assert!(runtime.is_none(), "a simulation is already running");
+        *runtime = Some(SimulatedRuntime {
+            now: Duration::ZERO,
+            tasks: Vec::new(),
+        });
+    });
+}
+
+pub(crate) fn stop_simulation() {
+    SIMULATED.take();
+}
+
+pub(crate) fn advance(duration: Duration) {
+    SIMULATED.with_borrow_mut(|runtime| {
+        if let Some(runtime) = runtime {
+            runtime.now += duration;
+        }
+    });
+}
+
+/// Polls every simulated task once. Tasks spawned along the way are first
+/// polled on the next call.
+pub(crate) fn poll_tasks(cx: &mut Context<'_>) {
+    // collected first so tasks can spawn or drop tasks while they're polled
+    let tasks: Vec<Rc<SimulatedTask>> = SIMULATED.with_borrow_mut(|runtime| {
+        let Some(runtime) = runtime else {
+            return Vec::new();
+        };
+        runtime.tasks.retain(|task| task.strong_count() > 0);
+        runtime.tasks.iter().filter_map(Weak::upgrade).collect()
+    });
+
+    for task in tasks {
+        let mut task = task.borrow_mut();
+        let finished = task
+            .as_mut()
+            .is_some_and(|future| future.as_mut().poll(cx).is_ready());
+        if finished {
+            *task = None;
+        }
+    }
+}
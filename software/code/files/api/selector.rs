@@ -0,0 +1,310 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
+
+use log::{info, warn};
+use vexide::{
+    color::Rgb,
+    controller::Controller,
+    display::{Display, Font, FontFamily, FontSize, Rect, Text, TouchState},
+    task::{Task, spawn},
+    time::sleep,
+};
+
+use crate::subsystems::{Color, RobotSettings};
+
+/// An autonomous route that can be picked before the match. `id` is whatever
+/// the robot matches on to run it.
+#[derive(Clone, Copy)]
+pub struct Route<T> {
+    pub id: T,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The alliance the route is for, `None` if it works for either.
+    pub color: Option<Color>,
+}
+
+impl<T> Route<T> {
+    pub const fn new(
+        id: T,
+        name: &'static str,
+        description: &'static str,
+        color: Option<Color>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            description,
+            color,
+        }
+    }
+}
+
+/// Lists the routes as buttons on the brain's touchscreen and remembers which
+/// one was pressed. Picking a route with an alliance also switches the robot's
+/// color so color sorting and mirroring follow along, and routes that work for
+/// either alliance use the one set with the alliance button.
+pub struct AutonSelector<T: Copy + PartialEq + 'static> {
+    routes: Rc<Vec<Route<T>>>,
+    selected: Rc<Cell<usize>>,
+    settings: Rc<RefCell<RobotSettings>>,
+    _task: Task<()>,
+}
+
+impl<T: Copy + PartialEq + 'static> AutonSelector<T> {
+    const BUTTON_WIDTH: i16 = 200;
+    const BUTTON_HEIGHT: i16 = 36;
+    const MARGIN: i16 = 6;
+
+    const RED: Rgb<u8> = Rgb::new(157, 34, 53);
+    const BLUE: Rgb<u8> = Rgb::new(30, 80, 170);
+    const NEUTRAL: Rgb<u8> = Rgb::new(70, 70, 70);
+    const HIGHLIGHT: Rgb<u8> = Rgb::new(255, 215, 0);
+    const TEXT: Rgb<u8> = Rgb::new(255, 255, 255);
+    const BACKGROUND: Rgb<u8> = Rgb::new(0, 0, 0);
+
+    /// The route with the id `default` starts selected, or the first route if
+    /// none has it. Only as many routes as fit in one column of the screen are
+    /// shown.
+    pub fn new(
+        mut display: Display,
+        routes: Vec<Route<T>>,
+        default: T,
+        settings: Rc<RefCell<RobotSettings>>,
+    ) -> Self {
+        let routes = Rc::new(routes);
+        let index = match routes.iter().position(|route| route.id == default) {
+            Some(index) => index,
+            None => {
+                warn!("The default auton isn't one of the routes, starting on the first");
+                0
+            }
+        };
+        let selected = Rc::new(Cell::new(index));
+        if !routes.is_empty() {
+            Self::select(&routes, &selected, &settings, index);
+        }
+
+        Self {
+            routes: routes.clone(),
+            selected: selected.clone(),
+            settings: settings.clone(),
+            _task: spawn(async move {
+                let mut drawn = None;
+                let mut was_pressed = false;
+
+                loop {
+                    let touch = display.touch_status();
+                    let pressed = touch.state != TouchState::Released;
+
+                    // only the start of a press counts so holding doesn't repeat
+                    if pressed && !was_pressed {
+                        let (x, y) = (touch.point.x, touch.point.y);
+                        if Self::on_alliance_button(x, y) {
+                            Self::swap_alliance(&settings);
+                        } else if let Some(index) =
+                            Self::button_at(x, y).filter(|&index| index < routes.len())
+                        {
+                            Self::select(&routes, &selected, &settings, index);
+                        }
+                    }
+                    was_pressed = pressed;
+
+                    // redraws after a touch or a change from the controller
+                    let state = (selected.get(), settings.borrow().color);
+                    if drawn != Some(state) {
+                        Self::draw(&mut display, &routes, state.0, state.1);
+                        drawn = Some(state);
+                    }
+
+                    sleep(Duration::from_millis(50)).await;
+                }
+            }),
+        }
+    }
+
+    pub fn selected(&self) -> Option<T> {
+        self.routes.get(self.selected.get()).map(|route| route.id)
+    }
+
+    pub fn route(&self) -> Option<&Route<T>> {
+        self.routes.get(self.selected.get())
+    }
+
+    /// Fallback for when the brain's screen can't be reached. Shows the route
+    /// on the controller and cycles through them with left and right, and up
+    /// swaps the alliance. Runs until cancelled, so it fits in
+    /// `Compete::disabled`.
+    pub async fn controller_select(&self, controller: &mut Controller) {
+        let mut shown = None;
+
+        loop {
+            let state = controller.state().unwrap_or_default();
+            let count = self.routes.len();
+
+            if count > 0 {
+                let current = self.selected.get();
+                if state.button_right.is_now_pressed() {
+                    self.pick((current + 1) % count);
+                } else if state.button_left.is_now_pressed() {
+                    self.pick((current + count - 1) % count);
+                }
+            }
+            if state.button_up.is_now_pressed() {
+                Self::swap_alliance(&self.settings);
+            }
+
+            let current = (self.selected.get(), self.settings.borrow().color);
+            if shown != Some(current) {
+                if let Some(route) = self.route() {
+                    let alliance = match current.1 {
+                        Color::Red => "Red      Up swaps",
+                        Color::Blue => "Blue     Up swaps",
+                    };
+                    _ = controller.screen.clear_screen().await;
+                    _ = controller.screen.set_text(route.name, 1, 1).await;
+                    _ = controller.screen.set_text("< L      R >", 2, 1).await;
+                    _ = controller.screen.set_text(alliance, 3, 1).await;
+                }
+                shown = Some(current);
+            }
+
+            sleep(Controller::UPDATE_INTERVAL).await;
+        }
+    }
+
+    fn pick(&self, index: usize) {
+        Self::select(&self.routes, &self.selected, &self.settings, index);
+    }
+
+    fn select(
+        routes: &[Route<T>],
+        selected: &Cell<usize>,
+        settings: &RefCell<RobotSettings>,
+        index: usize,
+    ) {
+        selected.set(index);
+
+        let route = &routes[index];
+        if let Some(color) = route.color {
+            settings.borrow_mut().color = color;
+        }
+        info!("Selected auton: {}", route.name);
+    }
+
+    fn swap_alliance(settings: &RefCell<RobotSettings>) {
+        let mut settings = settings.borrow_mut();
+        let (color, name) = match settings.color {
+            Color::Red => (Color::Blue, "blue"),
+            Color::Blue => (Color::Red, "red"),
+        };
+        settings.color = color;
+        info!("Alliance: {}", name);
+    }
+
+    // bottom right, under the selected route's description
+    fn alliance_button() -> ([i16; 2], [i16; 2]) {
+        let left = Self::MARGIN * 3 + Self::BUTTON_WIDTH;
+        let bottom = Display::HEIGHT - Self::MARGIN;
+
+        (
+            [left, bottom - Self::BUTTON_HEIGHT],
+            [Display::WIDTH - Self::MARGIN, bottom],
+        )
+    }
+
+    fn on_alliance_button(x: i16, y: i16) -> bool {
+        let ([left, top], [right, bottom]) = Self::alliance_button();
+        (left..right).contains(&x) && (top..bottom).contains(&y)
+    }
+
+    fn button_at(x: i16, y: i16) -> Option<usize> {
+        let top = Display::HEADER_HEIGHT + Self::MARGIN;
+        if !(Self::MARGIN..Self::MARGIN + Self::BUTTON_WIDTH).contains(&x) || y < top {
+            return None;
+        }
+
+        Some(((y - top) / (Self::BUTTON_HEIGHT + Self::MARGIN)) as usize)
+    }
+
+    fn draw(display: &mut Display, routes: &[Route<T>], selected: usize, alliance: Color) {
+        display.erase(Self::BACKGROUND);
+
+        let font = Font::new(FontSize::SMALL, FontFamily::Proportional);
+        let mut top = Display::HEADER_HEIGHT + Self::MARGIN;
+
+        for (index, route) in routes.iter().enumerate() {
+            if top + Self::BUTTON_HEIGHT > Display::HEIGHT {
+                break;
+            }
+
+            let color = match route.color {
+                Some(Color::Red) => Self::RED,
+                Some(Color::Blue) => Self::BLUE,
+                None => Self::NEUTRAL,
+            };
+            let button = Rect::new(
+                [Self::MARGIN, top],
+                [Self::MARGIN + Self::BUTTON_WIDTH, top + Self::BUTTON_HEIGHT],
+            );
+
+            display.fill(&button, color);
+            if index == selected {
+                display.stroke(&button, Self::HIGHLIGHT);
+            }
+            display.draw_text(
+                &Text::new(route.name, font, [Self::MARGIN * 2, top + Self::MARGIN * 2]),
+                Self::TEXT,
+                None,
+            );
+
+            top += Self::BUTTON_HEIGHT + Self::MARGIN;
+        }
+
+        if let Some(route) = routes.get(selected) {
+            let left = Self::MARGIN * 3 + Self::BUTTON_WIDTH;
+            let top = Display::HEADER_HEIGHT + Self::MARGIN;
+            let title = Font::new(FontSize::MEDIUM, FontFamily::Proportional);
+
+            display.draw_text(
+                &Text::new(route.name, title, [left, top]),
+                Self::HIGHLIGHT,
+                None,
+            );
+            // the screen doesn't wrap text, so the description goes in lines of
+            // whole words
+            let mut line = String::new();
+            let mut line_top = top + Self::BUTTON_HEIGHT;
+            for word in route.description.split_whitespace() {
+                if line.len() + word.len() > 36 {
+                    display.draw_text(&Text::new(&line, font, [left, line_top]), Self::TEXT, None);
+                    line.clear();
+                    line_top += 18;
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+            }
+            display.draw_text(&Text::new(&line, font, [left, line_top]), Self::TEXT, None);
+        }
+
+        let (start, end) = Self::alliance_button();
+        let (color, name) = match alliance {
+            Color::Red => (Self::RED, "Red alliance"),
+            Color::Blue => (Self::BLUE, "Blue alliance"),
+        };
+        display.fill(&Rect::new(start, end), color);
+        display.draw_text(
+            &Text::new(
+                name,
+                font,
+                [start[0] + Self::MARGIN, start[1] + Self::MARGIN * 2],
+            ),
+            Self::TEXT,
+            None,
+        );
+    }
+}
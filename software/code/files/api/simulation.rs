@@ -0,0 +1,498 @@
+use std::{
+    f64::consts::{PI, TAU},
+    future::{Future, pending},
+    pin::pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use uom::si::{
+    angle::radian,
+    angular_velocity::radian_per_second,
+    f64::{Angle, AngularVelocity, Length, Velocity},
+    length::meter,
+    velocity::meter_per_second,
+};
+use vexide::{
+    math::Direction,
+    prelude::{Gearset, Motor},
+};
+
+use crate::{
+    hardware::{
+        device::EncoderDevice,
+        mock::{MockEncoder, MockImu, MockMotor},
+    },
+    localization::{pose::Pose, vec2::Vec2},
+    runtime,
+};
+
+// Stall torque of a V5 motor with the red cartridge, the other cartridges trade
+// torque for speed from here.
+const RED_STALL_TORQUE: f64 = 2.1; // N*m
+
+/// Physical constants of a simulated tank drive.
+#[derive(Clone, Copy)]
+pub struct SimulationSettings {
+    pub gearset: Gearset,
+    /// Ratio of wheel speed to motor output speed.
+    pub gear_ratio: f64,
+    pub wheel_diameter: Length,
+    pub track: Length,
+    /// Mass in kilograms.
+    pub mass: f64,
+    /// Moment of inertia about the turning center in kilogram meters squared.
+    pub moment_of_inertia: f64,
+    /// Fraction of velocity lost every second to rolling resistance and scrub.
+    pub drag: f64,
+}
+
+impl SimulationSettings {
+    pub fn new(gearset: Gearset, wheel_diameter: Length, track: Length) -> Self {
+        Self {
+            gearset,
+            gear_ratio: 1.0,
+            wheel_diameter,
+            track,
+            mass: 6.8,
+            moment_of_inertia: 0.16,
+            drag: 0.5,
+        }
+    }
+
+    fn max_rpm(&self) -> f64 {
+        match self.gearset {
+            Gearset::Red => Gearset::MAX_RED_RPM,
+            Gearset::Green => Gearset::MAX_GREEN_RPM,
+            Gearset::Blue => Gearset::MAX_BLUE_RPM,
+        }
+    }
+
+    fn stall_torque(&self) -> f64 {
+        RED_STALL_TORQUE * Gearset::MAX_RED_RPM / self.max_rpm()
+    }
+}
+
+/// A tracking wheel whose encoder is driven by the simulation.
+pub struct SimulatedWheel {
+    pub encoder: MockEncoder,
+    /// The same direction the `TrackingWheel` reading it is given.
+    pub direction: Direction,
+    pub wheel_diameter: Length,
+    pub from_center: Vec2<Length>,
+    pub angle: Angle,
+}
+
+/// Rigid body model of a tank drive that reads the voltages given to mock motors
+/// and writes back the motor, encoder and IMU readings for its new state.
+pub struct DrivetrainModel {
+    settings: SimulationSettings,
+    left: Vec<MockMotor>,
+    right: Vec<MockMotor>,
+    imu: MockImu,
+    wheels: Vec<SimulatedWheel>,
+    pose: Pose,
+}
+
+impl DrivetrainModel {
+    pub fn new(
+        settings: SimulationSettings,
+        left: Vec<MockMotor>,
+        right: Vec<MockMotor>,
+        imu: MockImu,
+        wheels: Vec<SimulatedWheel>,
+        start: Pose,
+    ) -> Self {
+        let model = Self {
+            settings,
+            left,
+            right,
+            imu,
+            wheels,
+            pose: start,
+        };
+        model.write_imu();
+
+        model
+    }
+
+    /// The true pose of the simulated robot.
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+
+    pub fn step(&mut self, dt: Duration) {
+        let dt = dt.as_secs_f64();
+        let radius = self.settings.wheel_diameter.get::<meter>() / 2.0;
+        let half_track = self.settings.track.get::<meter>() / 2.0;
+
+        let v = self.pose.vf.get::<meter_per_second>();
+        let omega = self.pose.omega.get::<radian_per_second>();
+
+        // wheel speeds converted back to motor output RPM
+        let to_rpm = 60.0 / (TAU * radius * self.settings.gear_ratio);
+        let left_rpm = (v - omega * half_track) * to_rpm;
+        let right_rpm = (v + omega * half_track) * to_rpm;
+
+        let force_left = self.side_force(&self.left, left_rpm, radius);
+        let force_right = self.side_force(&self.right, right_rpm, radius);
+
+        let acceleration = (force_left + force_right) / self.settings.mass;
+        let alpha = (force_right - force_left) * half_track / self.settings.moment_of_inertia;
+
+        let damping = (1.0 - self.settings.drag * dt).max(0.0);
+        let v = (v + acceleration * dt) * damping;
+        let omega = (omega + alpha * dt) * damping;
+
+        // integrate along the arc traveled during the step
+        let dh = omega * dt;
+        let ds = v * dt;
+        let (dx, dy) = if dh.abs() < 1e-9 {
+            (ds, 0.0)
+        } else {
+            (ds * dh.sin() / dh, ds * (1.0 - dh.cos()) / dh)
+        };
+
+        let h = self.pose.h.get::<radian>();
+        let (sin, cos) = h.sin_cos();
+        self.pose = Pose {
+            x: self.pose.x + Length::new::<meter>(cos * dx - sin * dy),
+            y: self.pose.y + Length::new::<meter>(sin * dx + cos * dy),
+            h: Angle::new::<radian>(h + dh),
+            vf: Velocity::new::<meter_per_second>(v),
+            vs: Velocity::new::<meter_per_second>(0.0),
+            omega: AngularVelocity::new::<radian_per_second>(omega),
+        };
+
+        let left_rpm = (v - omega * half_track) * to_rpm;
+        let right_rpm = (v + omega * half_track) * to_rpm;
+        let motor_turn = |rpm: f64| Angle::new::<radian>(rpm / 60.0 * TAU * dt);
+
+        for motor in self.left.iter() {
+            let position = motor.state().position + motor_turn(left_rpm);
+            motor.set_measured(left_rpm, position);
+        }
+        for motor in self.right.iter() {
+            let position = motor.state().position + motor_turn(right_rpm);
+            motor.set_measured(right_rpm, position);
+        }
+
+        for wheel in self.wheels.iter() {
+            let offset = wheel.from_center;
+            let dx_wheel = dx - dh * offset.y.get::<meter>();
+            let dy_wheel = dy + dh * offset.x.get::<meter>();
+            let (sin, cos) = wheel.angle.get::<radian>().sin_cos();
+            let traveled = dx_wheel * cos + dy_wheel * sin;
+
+            let circumference = wheel.wheel_diameter.get::<meter>() * PI;
+            let sign = match wheel.direction {
+                Direction::Forward => 1.0,
+                Direction::Reverse => -1.0,
+            };
+            let position = wheel.encoder.position().unwrap_or_default()
+                + Angle::new::<radian>(sign * traveled / circumference * TAU);
+            wheel.encoder.set_measured(position);
+        }
+
+        self.write_imu();
+    }
+
+    // total force from one side given its motors' output RPM
+    fn side_force(&self, motors: &[MockMotor], rpm: f64, radius: f64) -> f64 {
+        let max_rpm = self.settings.max_rpm();
+        let stall_torque = self.settings.stall_torque();
+
+        let torque: f64 = motors
+            .iter()
+            .map(|motor| {
+                let state = motor.state();
+                let voltage = match state.target_velocity {
+                    // approximates the motor's built in velocity controller
+                    Some(target) => Motor::V5_MAX_VOLTAGE * (2.0 * target - rpm) / max_rpm,
+                    None => state.voltage,
+                }
+                .clamp(-Motor::V5_MAX_VOLTAGE, Motor::V5_MAX_VOLTAGE);
+
+                stall_torque * (voltage / Motor::V5_MAX_VOLTAGE - rpm / max_rpm)
+            })
+            .sum();
+
+        torque / (self.settings.gear_ratio * radius)
+    }
+
+    // the IMU is clockwise positive, reported so `Imu::rotation` reads the model heading
+    fn write_imu(&self) {
+        self.imu
+            .set_measured(Angle::new::<radian>(TAU) - self.pose.h);
+    }
+}
+
+/// Runs the API against a `DrivetrainModel` on simulated time. While it's
+/// alive the clock, `sleep` and background tasks in `runtime` all follow the
+/// simulation, so build the drivetrain and anything else that spawns tasks
+/// after it. The model only moves in fixed steps, so the same run always ends
+/// the same way.
+pub struct Simulation {
+    model: DrivetrainModel,
+    elapsed: Duration,
+    trace_period: Duration,
+    next_trace: Option<Duration>,
+    trace: Vec<(Duration, Pose)>,
+}
+
+impl Simulation {
+    pub const STEP: Duration = Duration::from_millis(1);
+
+    pub fn new(model: DrivetrainModel) -> Self {
+        runtime::start_simulation();
+
+        Self {
+            model,
+            elapsed: Duration::ZERO,
+            trace_period: Duration::ZERO,
+            next_trace: None,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Records the model's pose every `period`.
+    pub fn trace(&mut self, period: Duration) -> &mut Self {
+        self.trace_period = period;
+        self.next_trace = Some(self.elapsed);
+        self
+    }
+
+    /// The true pose of the simulated robot.
+    pub fn pose(&self) -> Pose {
+        self.model.pose()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The poses recorded so far along with when they were recorded.
+    pub fn poses(&self) -> &[(Duration, Pose)] {
+        &self.trace
+    }
+
+    /// Runs `future` along with the background tasks until it finishes, or
+    /// `None` if it's still going after `limit` of simulated time.
+    pub fn run<F: Future>(&mut self, future: F, limit: Duration) -> Option<F::Output> {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        let end = self.elapsed + limit;
+
+        loop {
+            // the tasks go first so localizers see the last step before the
+            // motions do
+            runtime::poll_tasks(&mut cx);
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return Some(output);
+            }
+            if self.elapsed >= end {
+                return None;
+            }
+
+            self.step();
+        }
+    }
+
+    /// Lets the background tasks run for `duration` without anything else.
+    pub fn idle(&mut self, duration: Duration) {
+        self.run(pending::<()>(), duration);
+    }
+
+    fn step(&mut self) {
+        if let Some(next) = self.next_trace.filter(|&next| self.elapsed >= next) {
+            self.trace.push((self.elapsed, self.model.pose()));
+            self.next_trace = Some(next + self.trace_period);
+        }
+
+        self.model.step(Self::STEP);
+        self.elapsed += Self::STEP;
+        runtime::advance(Self::STEP);
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        runtime::stop_simulation();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::time::Duration;
+
+    use uom::si::{
+        angle::{degree, radian},
+        f64::{Angle, Length},
+        length::{inch, meter, millimeter},
+    };
+    use vexide::{math::Direction, prelude::Gearset};
+
+    use super::{DrivetrainModel, SimulatedWheel, Simulation, SimulationSettings};
+    use crate::{
+        controllers::pid::Pid,
+        hardware::{
+            imu::Imu,
+            mock::{MockEncoder, MockImu, MockMotor},
+            motor_group::MotorGroup,
+            tracking_wheel::TrackingWheel,
+        },
+        localization::{odometry::Odometry, pose::Pose, vec2::Vec2},
+        motion::linear::Linear,
+        subsystems::drivetrain::Drivetrain,
+    };
+
+    fn origin() -> Pose {
+        Pose::new(Length::default(), Length::default(), Angle::default())
+    }
+
+    fn settings() -> SimulationSettings {
+        SimulationSettings::new(
+            Gearset::Blue,
+            Length::new::<inch>(2.5),
+            Length::new::<inch>(12.0),
+        )
+    }
+
+    fn motors() -> Vec<MockMotor> {
+        (0..3).map(|_| MockMotor::new()).collect()
+    }
+
+    // drives the model open loop for a second
+    fn drive(left: f64, right: f64) -> Pose {
+        let (left_motors, right_motors) = (motors(), motors());
+        let mut model = DrivetrainModel::new(
+            settings(),
+            left_motors.clone(),
+            right_motors.clone(),
+            MockImu::new(),
+            Vec::new(),
+            origin(),
+        );
+
+        let mut left_group = MotorGroup::<MockMotor>::new(left_motors, None);
+        let mut right_group = MotorGroup::<MockMotor>::new(right_motors, None);
+        left_group.set_voltage(left);
+        right_group.set_voltage(right);
+        for _ in 0..1000 {
+            model.step(Simulation::STEP);
+        }
+
+        model.pose()
+    }
+
+    #[test]
+    fn equal_voltages_drive_straight() {
+        let pose = drive(6.0, 6.0);
+
+        assert!(pose.x.get::<meter>() > 0.5, "{:?}", pose.x);
+        assert!(pose.y.get::<meter>().abs() < 1e-9, "{:?}", pose.y);
+        assert!(pose.h.get::<radian>().abs() < 1e-9, "{:?}", pose.h);
+    }
+
+    #[test]
+    fn opposite_voltages_turn_in_place() {
+        let pose = drive(-6.0, 6.0);
+
+        assert!(pose.x.get::<meter>().abs() < 1e-9, "{:?}", pose.x);
+        assert!(pose.y.get::<meter>().abs() < 1e-9, "{:?}", pose.y);
+        // right side forward turns counterclockwise
+        assert!(pose.h.get::<radian>() > 1.0, "{:?}", pose.h);
+    }
+
+    /// A simulated robot with odometry on two diagonal tracking wheels, for
+    /// tests of motions that need a drivetrain to follow them.
+    pub(crate) fn simulated_drivetrain(
+        start: Pose,
+    ) -> (Simulation, Drivetrain<MockMotor, Odometry>) {
+        let (left, right, imu) = (motors(), motors(), MockImu::new());
+        let wheels = [45.0, -45.0].map(|angle: f64| SimulatedWheel {
+            encoder: MockEncoder::new(),
+            direction: Direction::Forward,
+            wheel_diameter: Length::new::<millimeter>(60.0),
+            from_center: Vec2::new(
+                Length::new::<inch>(-5.9),
+                Length::new::<inch>(angle.signum()),
+            ),
+            angle: Angle::new::<degree>(angle),
+        });
+        let tracking_wheels = wheels.each_ref().map(|wheel| {
+            TrackingWheel::with_encoder(
+                wheel.encoder.clone(),
+                wheel.direction,
+                wheel.wheel_diameter,
+                wheel.from_center,
+                wheel.angle,
+            )
+        });
+
+        let model = DrivetrainModel::new(
+            settings(),
+            left.clone(),
+            right.clone(),
+            imu.clone(),
+            Vec::from(wheels),
+            start,
+        );
+        let sim = Simulation::new(model);
+
+        let [wheel_1, wheel_2] = tracking_wheels;
+        let odometry = Odometry::new(
+            start,
+            Duration::from_millis(10),
+            wheel_1,
+            wheel_2,
+            Imu::new(vec![imu]),
+        );
+        let dt = Drivetrain::new(
+            MotorGroup::new(left, None),
+            MotorGroup::new(right, None),
+            odometry,
+            Length::new::<inch>(2.5),
+            Length::new::<inch>(12.0),
+        );
+
+        (sim, dt)
+    }
+
+    // drives 24 inches, returning the true pose and the one odometry ended up
+    // with
+    fn drive_closed_loop() -> (Pose, Pose) {
+        let (mut sim, mut dt) = simulated_drivetrain(origin());
+
+        let mut linear = Linear::new(Pid::new(46.0, 0.0, 3.95, 12.0), Length::new::<inch>(0.5));
+        linear.timeout(Duration::from_secs(3));
+        let finished = sim.run(
+            linear.drive_distance(&mut dt, Length::new::<inch>(24.0)),
+            Duration::from_secs(5),
+        );
+        assert!(finished.is_some(), "the motion never ended");
+
+        (sim.pose(), dt.pose())
+    }
+
+    #[test]
+    fn motions_run_on_simulated_time() {
+        let (pose, estimate) = drive_closed_loop();
+
+        assert!((pose.x.get::<inch>() - 24.0).abs() < 1.0, "{:?}", pose.x);
+        assert!(
+            (estimate.x - pose.x).abs().get::<inch>() < 0.5,
+            "{:?}",
+            estimate.x
+        );
+    }
+
+    #[test]
+    fn runs_are_deterministic() {
+        let (first, _) = drive_closed_loop();
+        let (second, _) = drive_closed_loop();
+
+        assert_eq!(first.x, second.x);
+        assert_eq!(first.h, second.h);
+    }
+}
@@ -9,31 +9,32 @@ use uom::si::{
 use vexide::prelude::Motor;
 
 use crate::{
-    hardware::motor_group::MotorGroup,
-    localization::{odometry::Odometry, pose::Pose},
+    hardware::{device::MotorDevice, motor_group::MotorGroup},
+    localization::{localizer::Localizer, pose::Pose},
     mappings::DriveMode,
+    runtime::Instant,
 };
 
-pub struct Drivetrain {
-    pub left: MotorGroup,
-    pub right: MotorGroup,
-    odometry: Odometry,
+pub struct Drivetrain<M: MotorDevice = Motor, L: Localizer = Box<dyn Localizer>> {
+    pub left: MotorGroup<M>,
+    pub right: MotorGroup<M>,
+    localizer: L,
     wheel_circum: Length,
     track: Length,
 }
 
-impl Drivetrain {
+impl<M: MotorDevice, L: Localizer> Drivetrain<M, L> {
     pub fn new(
-        left: MotorGroup,
-        right: MotorGroup,
-        odometry: Odometry,
+        left: MotorGroup<M>,
+        right: MotorGroup<M>,
+        localizer: L,
         wheel_diameter: Length,
         track: Length,
     ) -> Self {
         Self {
             left,
             right,
-            odometry,
+            localizer,
             wheel_circum: wheel_diameter * PI,
             track,
         }
@@ -108,16 +109,35 @@ impl Drivetrain {
     }
 
     pub fn pose(&self) -> Pose {
-        self.odometry.get_pose()
+        self.localizer.pose()
     }
 
     pub fn set_pose(&mut self, pose: Pose) {
-        self.odometry.set_pose(pose);
+        self.localizer.set_pose(pose);
+    }
+
+    /// Where the robot was at `time`, for lining up late sensor readings.
+    pub fn pose_at(&self, time: Instant) -> Option<Pose> {
+        self.localizer.pose_at(time)
+    }
+
+    /// The localizer itself, for what only it has like `Odometry::stats` or
+    /// `Ekf::variance`.
+    pub fn localizer(&self) -> &L {
+        &self.localizer
+    }
+
+    pub fn localizer_mut(&mut self) -> &mut L {
+        &mut self.localizer
     }
 
     pub fn track(&mut self) -> Length {
         self.track
     }
+
+    pub fn wheel_circum(&self) -> Length {
+        self.wheel_circum
+    }
 }
 
 /// Applies an acceleration function to the given power value.
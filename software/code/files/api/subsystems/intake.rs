@@ -1,42 +1,73 @@
-use std::{cell::RefCell, rc::Rc, time::Duration};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
 
 use log::info;
-use vexide::{
-    prelude::{AdiDigitalOut, Motor, OpticalSensor},
-    task::{Task, spawn},
-    time::sleep,
-};
 
 use super::RobotSettings;
-use crate::subsystems::Color;
+use crate::{
+    controllers::feedforward::FeedforwardSample,
+    hardware::{
+        device::{DigitalOutDevice, MotorDevice, OpticalDevice},
+        motor_group::MotorGroup,
+    },
+    motion::characterization::Characterization,
+    runtime::{Task, sleep, spawn},
+    subsystems::Color,
+};
+
+// handed between the intake and its task, numbered by the call that asked for
+// the run
+type Run<T> = Rc<RefCell<Option<(u32, T)>>>;
 
 pub struct Intake {
     voltage: Rc<RefCell<f64>>,
-    _task: Task<()>,
+    // the task owns the motors, so characterizing them is handed to it and
+    // the samples are handed back. The numbers keep samples from a run nobody
+    // is waiting on anymore from being mistaken for the next one's.
+    characterization: Run<Characterization>,
+    samples: Run<Vec<FeedforwardSample>>,
+    runs: Cell<u32>,
+    _task: Task,
 }
 
 impl Intake {
-    pub fn new(
-        mut top: Motor,
-        mut bottom: Motor,
-        mut door: AdiDigitalOut,
-        color_sort: OpticalSensor,
+    pub fn new<
+        M: MotorDevice + 'static,
+        D: DigitalOutDevice + 'static,
+        O: OpticalDevice + 'static,
+    >(
+        mut motors: MotorGroup<M>,
+        mut door: D,
+        color_sort: O,
         delay: Duration,
         settings: Rc<RefCell<RobotSettings>>,
     ) -> Self {
         let voltage = Rc::new(RefCell::new(0.0));
+        let characterization = Rc::new(RefCell::new(None));
+        let samples = Rc::new(RefCell::new(None));
 
         Self {
             voltage: voltage.clone(),
+            characterization: characterization.clone(),
+            samples: samples.clone(),
+            runs: Cell::new(0),
             _task: spawn(async move {
                 let mut ball_timer = Duration::ZERO;
 
                 loop {
+                    let requested = characterization.take();
+                    if let Some((run, characterization)) = requested {
+                        let run_samples = characterization.run(&mut motors).await;
+                        samples.replace(Some((run, run_samples)));
+                    }
+
                     let voltage = *voltage.borrow();
                     let settings = *settings.borrow();
 
-                    _ = top.set_voltage(voltage);
-                    _ = bottom.set_voltage(voltage);
+                    motors.set_voltage(voltage);
 
                     if settings.enable_color {
                         // Red hue -> 0-60
@@ -53,16 +84,16 @@ impl Intake {
                             if alliance.contains(&hue) {
                                 info!("red: {}", proximity);
                                 sleep(delay).await;
-                                _ = door.set_low();
+                                door.set_low();
                             } else if opposing.contains(&hue) {
                                 info!("blue: {}", proximity);
-                                _ = door.set_high();
+                                door.set_high();
                             }
                             ball_timer = Duration::ZERO;
                         } else if ball_timer > Duration::from_millis(1000) {
-                            _ = door.set_low();
+                            door.set_low();
                             ball_timer = Duration::ZERO;
-                        } else if door.level().is_ok_and(|x| x.is_high()) {
+                        } else if door.is_high() {
                             ball_timer += Duration::from_millis(10);
                         }
 
@@ -79,5 +110,28 @@ impl Intake {
         self.voltage.replace(voltage)
     }
 
+    /// Runs `characterization` on the intake's motors, which ignore
+    /// `set_voltage` and color sorting until it's done. If an earlier call was
+    /// cancelled partway, its run finishes first and its samples are thrown
+    /// away.
+    pub async fn characterize(
+        &self,
+        characterization: &Characterization,
+    ) -> Vec<FeedforwardSample> {
+        let run = self.runs.get().wrapping_add(1);
+        self.runs.set(run);
+        self.samples.take();
+        self.characterization
+            .replace(Some((run, *characterization)));
+
+        loop {
+            sleep(Duration::from_millis(10)).await;
+            match self.samples.take() {
+                Some((finished, samples)) if finished == run => return samples,
+                _ => {}
+            }
+        }
+    }
+
     pub fn test_door(&mut self) {}
 }
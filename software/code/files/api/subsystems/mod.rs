@@ -7,7 +7,7 @@ pub struct RobotSettings {
     pub enable_color: bool,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Color {
     Red,
     Blue,
@@ -3,7 +3,6 @@ use std::f64::consts::{PI, TAU};
 use uom::si::{
     angle::radian,
     f64::{Angle, Length},
-    length::meter,
 };
 
 use crate::localization::{pose::Pose, vec2::Vec2};
@@ -17,8 +16,36 @@ pub fn wrapped(angle: f64) -> f64 {
 }
 
 pub fn angular_distance(pose: Pose, other: Vec2<Length>) -> Angle {
-    let pose = Vec2::new(pose.x.get::<meter>(), pose.y.get::<meter>());
-    let other = Vec2::new(other.x.get::<meter>(), other.y.get::<meter>());
+    pose.position().angle_to(other)
+}
+
+/// Solves an augmented matrix in place, `None` if the system is singular.
+pub fn gaussian_elimination(mut system: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let n = system.len();
+
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&a, &b| system[a][column].abs().total_cmp(&system[b][column].abs()))?;
+        if system[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        system.swap(column, pivot);
+
+        let (above, below) = system.split_at_mut(column + 1);
+        let pivot_row = &above[column];
+        for row in below.iter_mut() {
+            let factor = row[column] / pivot_row[column];
+            for (value, pivot) in row[column..].iter_mut().zip(&pivot_row[column..]) {
+                *value -= factor * pivot;
+            }
+        }
+    }
+
+    let mut values = vec![0.0; n];
+    for row in (0..n).rev() {
+        let rest: f64 = (row + 1..n).map(|k| system[row][k] * values[k]).sum();
+        values[row] = (system[row][n] - rest) / system[row][row];
+    }
 
-    Angle::new::<radian>(other.angular_distance(pose))
+    Some(values)
 }
@@ -4,7 +4,9 @@ use atum::{
     controllers::pid::Pid,
     localization::pose::Pose,
     motion::{linear::Linear, move_to::MoveTo, swing::Swing, turn::Turn},
+    routine::command::Routine,
 };
+use log::error;
 use uom::{
     ConstZero,
     si::{
@@ -15,53 +17,44 @@ use uom::{
 };
 use vexide::prelude::{Motor, sleep};
 
-const LINEAR_PID: Pid = Pid::new(46.0, 0.0, 3.95, 12.0);
-const ANGULAR_PID: Pid = Pid::new(19.0, 0.25, 1.4, 25.0);
+const QUAL: &str = include_str!("routines/qual.auton");
 
 const _SETTLE_LIN_VEL: f64 = 2.5; // INCHES
 const _SETTLE_ANG_VEL: f64 = 1.0; // DEGREES
 
-use crate::Robot;
+use crate::{
+    Robot,
+    tuning::{ANGULAR_PID, LINEAR_PID, PISTONS, interpreter},
+};
 
 impl Robot {
     pub async fn qual(&mut self) {
-        let _move_to = MoveTo::new(
-            Pid::new(30.0, 1.0, 6.0, 12.0),
-            Pid::new(21.0, 2.0, 0.0, 18.0),
-            Length::new::<inch>(1.0),
-        );
-
-        let mut linear = Linear::new(LINEAR_PID, Length::new::<inch>(0.5));
-
-        let mut turn = Turn::new(ANGULAR_PID, Angle::new::<degree>(1.0));
-
-        let dt = &mut self.drivetrain;
-
-        dt.set_pose(Pose::new(
-            Length::new::<inch>(84.0),
-            Length::new::<inch>(24.0),
-            Angle::new::<degree>(55.0),
-        ));
-
-        self.intake.set_voltage(Motor::V5_MAX_VOLTAGE);
-
-        linear
-            .timeout(Duration::from_millis(1000))
-            .drive_distance(dt, Length::new::<inch>(45.0))
-            .await;
-
-        linear
-            .timeout(Duration::from_millis(1000))
-            .drive_distance(dt, Length::new::<inch>(-20.0))
-            .await;
-
-        turn.timeout(Duration::from_millis(1000))
-            .turn_to(dt, Angle::new::<degree>(135.0))
-            .await;
-
-        linear
-            .timeout(Duration::from_millis(1000))
-            .drive_distance(dt, Length::new::<inch>(20.0))
+        let routine = match Routine::parse(QUAL).and_then(|routine| {
+            routine.validate(&PISTONS)?;
+            Ok(routine)
+        }) {
+            Ok(routine) => routine,
+            Err(err) => {
+                error!("Qual routine: {}", err);
+                return;
+            }
+        };
+
+        let mut interpreter = interpreter();
+
+        interpreter
+            .run(
+                &routine,
+                &mut self.drivetrain,
+                &self.intake,
+                &mut [
+                    ("lift", &mut self.lift),
+                    ("duck_bill", &mut self.duck_bill),
+                    ("match_loader", &mut self.match_loader),
+                    ("wing", &mut self.wing),
+                    ("brake", &mut self.brake),
+                ],
+            )
             .await;
     }
 
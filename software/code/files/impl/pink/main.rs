@@ -1,4 +1,5 @@
 mod autos;
+mod tuning;
 
 use std::{
     cell::RefCell,
@@ -13,6 +14,7 @@ use atum::{
     logger::Logger,
     mappings::{ControllerMappings, DriveMode},
     motion::move_to::MoveTo,
+    selector::{AutonSelector, Route},
     subsystems::{Color, RobotSettings, drivetrain::Drivetrain, intake::Intake},
     theme::STOUT_ROBOT,
 };
@@ -27,9 +29,29 @@ use uom::{
 };
 use vexide::prelude::*;
 
+#[derive(Clone, Copy, PartialEq)]
+enum Auton {
+    Qual,
+    Elims,
+    SafeQuals,
+    RushElims,
+    RushControl,
+    Skills,
+}
+
+const ROUTES: [Route<Auton>; 6] = [
+    Route::new(Auton::Qual, "Qual", "", None),
+    Route::new(Auton::Elims, "Elims", "", None),
+    Route::new(Auton::SafeQuals, "Safe Quals", "", None),
+    Route::new(Auton::RushElims, "Rush Elims", "", None),
+    Route::new(Auton::RushControl, "Rush Control", "", None),
+    Route::new(Auton::Skills, "Skills", "", None),
+];
+
 struct Robot {
     controller: Controller,
-    drivetrain: Drivetrain,
+    selector: AutonSelector<Auton>,
+    drivetrain: Drivetrain<Motor, Odometry>,
     intake: Intake,
     lift: AdiDigitalOut,
     duck_bill: AdiDigitalOut,
@@ -41,19 +63,23 @@ struct Robot {
 impl Compete for Robot {
     async fn autonomous(&mut self) {
         let time = Instant::now();
-        let path = 5;
-
-        match path {
-            0 => self.qual().await,
-            1 => self.elims().await,
-            2 => self.safequals().await,
-            3 => self.rushelims().await,
-            4 => self.rushcontrol().await,
-            5 => self.skills().await,
-            _ => (),
+
+        match self.selector.selected() {
+            Some(Auton::Qual) => self.qual().await,
+            Some(Auton::Elims) => self.elims().await,
+            Some(Auton::SafeQuals) => self.safequals().await,
+            Some(Auton::RushElims) => self.rushelims().await,
+            Some(Auton::RushControl) => self.rushcontrol().await,
+            Some(Auton::Skills) => self.skills().await,
+            None => (),
         }
 
         info!("Time elapsed: {:?}", time.elapsed());
+        info!("Odometry loop: {}", self.drivetrain.localizer().stats());
+    }
+
+    async fn disabled(&mut self) {
+        self.selector.controller_select(&mut self.controller).await;
     }
 
     async fn driver(&mut self) {
@@ -157,8 +183,14 @@ async fn main(peripherals: Peripherals) {
         enable_color: true,
     }));
 
-    let robot = Robot {
+    let mut robot = Robot {
         controller: peripherals.primary_controller,
+        selector: AutonSelector::new(
+            peripherals.display,
+            ROUTES.to_vec(),
+            Auton::Skills,
+            settings.clone(),
+        ),
         drivetrain: Drivetrain::new(
             MotorGroup::new(
                 vec![
@@ -182,6 +214,7 @@ async fn main(peripherals: Peripherals) {
             ),
             Odometry::new(
                 starting_position,
+                Duration::from_millis(10),
                 TrackingWheel::new(
                     peripherals.adi_a,
                     peripherals.adi_b,
@@ -210,8 +243,13 @@ async fn main(peripherals: Peripherals) {
             Length::new::<inch>(12.0),
         ),
         intake: Intake::new(
-            Motor::new(peripherals.port_1, Gearset::Blue, Direction::Forward),
-            Motor::new(peripherals.port_2, Gearset::Blue, Direction::Forward),
+            MotorGroup::new(
+                vec![
+                    Motor::new(peripherals.port_1, Gearset::Blue, Direction::Forward),
+                    Motor::new(peripherals.port_2, Gearset::Blue, Direction::Forward),
+                ],
+                None,
+            ),
             AdiDigitalOut::new(peripherals.adi_e),
             color_sort,
             Duration::from_millis(100),
@@ -224,5 +262,10 @@ async fn main(peripherals: Peripherals) {
         brake: AdiDigitalOut::new(adi_expander.adi_b),
     };
 
+    robot
+        .drivetrain
+        .localizer_mut()
+        .log_stats(Some(Duration::from_secs(10)));
+
     robot.compete().await;
 }
@@ -0,0 +1,36 @@
+//! Pink's gains and piston names. The simulator includes this file too, so a
+//! routine run on the computer uses the robot's own tuning.
+
+use atum::{
+    controllers::pid::Pid,
+    motion::{linear::Linear, move_to::MoveTo, swing::Swing, turn::Turn},
+    routine::interpreter::Interpreter,
+};
+use uom::si::{
+    angle::degree,
+    f64::{Angle, Length},
+    length::inch,
+};
+
+pub const LINEAR_PID: Pid = Pid::new(46.0, 0.0, 3.95, 12.0);
+pub const ANGULAR_PID: Pid = Pid::new(19.0, 0.25, 1.4, 25.0);
+
+// the names routines use for the robot's pistons
+pub const PISTONS: [&str; 5] = ["lift", "duck_bill", "match_loader", "wing", "brake"];
+
+/// The motions routines run with.
+pub fn interpreter() -> Interpreter {
+    Interpreter::new(
+        Linear::new(LINEAR_PID, Length::new::<inch>(0.5)),
+        Turn::new(ANGULAR_PID, Angle::new::<degree>(1.0)),
+        Swing::new(
+            Pid::new(1000.0, 150.0, 0.0, 90.0),
+            Angle::new::<degree>(1.0),
+        ),
+        MoveTo::new(
+            Pid::new(30.0, 1.0, 6.0, 12.0),
+            Pid::new(21.0, 2.0, 0.0, 18.0),
+            Length::new::<inch>(1.0),
+        ),
+    )
+}
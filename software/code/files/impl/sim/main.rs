@@ -0,0 +1,212 @@
+//! Runs a routine on the computer against a simulated pink robot and prints
+//! where it went, e.g. `sim impl/pink/routines/qual.auton`. With `--check` it
+//! only parses and validates every routine in a directory, e.g.
+//! `sim --check impl/pink/routines`, so routines can be checked before they
+//! go on the robot.
+
+#[path = "../pink/tuning.rs"]
+mod tuning;
+
+use std::{cell::RefCell, fs, path::Path, process::ExitCode, rc::Rc, time::Duration};
+
+use atum::{
+    hardware::{
+        imu::Imu,
+        mock::{MockDigitalOut, MockEncoder, MockImu, MockMotor, MockOptical},
+        motor_group::MotorGroup,
+        tracking_wheel::TrackingWheel,
+    },
+    localization::{odometry::Odometry, pose::Pose, vec2::Vec2},
+    routine::command::{Command, Routine},
+    simulation::{DrivetrainModel, SimulatedWheel, Simulation, SimulationSettings},
+    subsystems::{Color, RobotSettings, drivetrain::Drivetrain, intake::Intake},
+};
+use uom::{
+    ConstZero,
+    si::{
+        angle::degree,
+        f64::{Angle, Length},
+        length::{inch, millimeter},
+    },
+};
+use vexide::{math::Direction, prelude::Gearset};
+
+use crate::tuning::{PISTONS, interpreter};
+
+// autonomous is 15 seconds and skills a minute, anything past that is stuck
+const LIMIT: Duration = Duration::from_secs(60);
+const TRACE_PERIOD: Duration = Duration::from_millis(50);
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [flag, directory] if flag == "--check" => check(Path::new(directory)),
+        [path] => simulate(Path::new(path)),
+        _ => {
+            eprintln!("usage: sim <routine.auton>\n       sim --check <directory>");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// reads a routine and checks it only uses pistons the robot has
+fn load(path: &Path) -> Result<Routine, String> {
+    let source = fs::read_to_string(path)
+        .map_err(|err| format!("Couldn't read {}: {}", path.display(), err))?;
+
+    Routine::parse(&source)
+        .and_then(|routine| {
+            routine.validate(&PISTONS)?;
+            Ok(routine)
+        })
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+fn check(directory: &Path) -> ExitCode {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("Couldn't read {}: {}", directory.display(), err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "auton")
+        })
+        .collect();
+    paths.sort();
+
+    if paths.is_empty() {
+        eprintln!("No routines in {}", directory.display());
+        return ExitCode::FAILURE;
+    }
+
+    let mut failed = 0;
+    for path in paths.iter() {
+        match load(path) {
+            Ok(_) => println!("{}: ok", path.display()),
+            Err(err) => {
+                eprintln!("{}", err);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        eprintln!("{} of {} routines failed", failed, paths.len());
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn simulate(path: &Path) -> ExitCode {
+    let routine = match load(path) {
+        Ok(routine) => routine,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // the robot is placed wherever the routine says it starts
+    let starting_position = match routine.commands().next() {
+        Some(Command::SetPose(pose)) => *pose,
+        _ => Pose::new(Length::ZERO, Length::ZERO, Angle::ZERO),
+    };
+
+    let left: Vec<_> = (0..5).map(|_| MockMotor::new()).collect();
+    let right: Vec<_> = (0..5).map(|_| MockMotor::new()).collect();
+    let imu = MockImu::new();
+    let wheels = [(-1.00288550, 45.0), (1.00288550, -45.0)].map(|(y, angle)| SimulatedWheel {
+        encoder: MockEncoder::new(),
+        direction: Direction::Forward,
+        wheel_diameter: Length::new::<millimeter>(60.0),
+        from_center: Vec2::new(Length::new::<inch>(-5.93824103), Length::new::<inch>(y)),
+        angle: Angle::new::<degree>(angle),
+    });
+    let [wheel_1, wheel_2] = wheels.each_ref().map(|wheel| {
+        TrackingWheel::with_encoder(
+            wheel.encoder.clone(),
+            wheel.direction,
+            wheel.wheel_diameter,
+            wheel.from_center,
+            wheel.angle,
+        )
+    });
+
+    let mut sim = Simulation::new(DrivetrainModel::new(
+        SimulationSettings::new(
+            Gearset::Blue,
+            Length::new::<inch>(2.5),
+            Length::new::<inch>(12.0),
+        ),
+        left.clone(),
+        right.clone(),
+        imu.clone(),
+        Vec::from(wheels),
+        starting_position,
+    ));
+    sim.trace(TRACE_PERIOD);
+
+    // built after the simulation so their tasks run on its clock
+    let mut drivetrain = Drivetrain::new(
+        MotorGroup::new(left, None),
+        MotorGroup::new(right, None),
+        Odometry::new(
+            starting_position,
+            Duration::from_millis(10),
+            wheel_1,
+            wheel_2,
+            Imu::new(vec![imu]),
+        ),
+        Length::new::<inch>(2.5),
+        Length::new::<inch>(12.0),
+    );
+    let intake = Intake::new(
+        MotorGroup::new(vec![MockMotor::new(), MockMotor::new()], None),
+        MockDigitalOut::new(),
+        MockOptical::new(),
+        Duration::from_millis(100),
+        Rc::new(RefCell::new(RobotSettings {
+            color: Color::Red,
+            enable_color: true,
+        })),
+    );
+    let mut pistons = PISTONS.map(|_| MockDigitalOut::new());
+    let [lift, duck_bill, match_loader, wing, brake] = &mut pistons;
+
+    let mut interpreter = interpreter();
+
+    let finished = sim.run(
+        interpreter.run(
+            &routine,
+            &mut drivetrain,
+            &intake,
+            &mut [
+                ("lift", lift),
+                ("duck_bill", duck_bill),
+                ("match_loader", match_loader),
+                ("wing", wing),
+                ("brake", brake),
+            ],
+        ),
+        LIMIT,
+    );
+
+    for (time, pose) in sim.poses() {
+        println!("{:>7.3}s {}", time.as_secs_f64(), pose);
+    }
+    println!("Odometry: {}", drivetrain.pose());
+
+    if finished.is_none() {
+        eprintln!("Still running after {:?}", LIMIT);
+        return ExitCode::FAILURE;
+    }
+    println!("Finished in {:?}", sim.elapsed());
+
+    ExitCode::SUCCESS
+}
@@ -21,7 +21,7 @@ use vexide::prelude::*;
 
 struct Robot {
     controller: Controller,
-    drivetrain: Drivetrain,
+    drivetrain: Drivetrain<Motor, Odometry>,
     intake: Intake,
     lift: AdiDigitalOut,
     duck_bill: AdiDigitalOut,
@@ -104,7 +104,7 @@ async fn main(peripherals: Peripherals) {
         enable_color: true,
     }));
 
-    let robot = Robot {
+    let mut robot = Robot {
         controller: peripherals.primary_controller,
         drivetrain: Drivetrain::new(
             MotorGroup::new(
@@ -129,6 +129,7 @@ async fn main(peripherals: Peripherals) {
             ),
             Odometry::new(
                 starting_position,
+                Duration::from_millis(10),
                 TrackingWheel::new(
                     peripherals.adi_a,
                     peripherals.adi_b,
@@ -157,8 +158,13 @@ async fn main(peripherals: Peripherals) {
             Length::new::<inch>(12.0),
         ),
         intake: Intake::new(
-            Motor::new(peripherals.port_1, Gearset::Blue, Direction::Reverse),
-            Motor::new(peripherals.port_2, Gearset::Blue, Direction::Forward),
+            MotorGroup::new(
+                vec![
+                    Motor::new(peripherals.port_1, Gearset::Blue, Direction::Reverse),
+                    Motor::new(peripherals.port_2, Gearset::Blue, Direction::Forward),
+                ],
+                None,
+            ),
             AdiDigitalOut::new(peripherals.adi_e),
             color_sort,
             Duration::from_millis(100),
@@ -170,5 +176,10 @@ async fn main(peripherals: Peripherals) {
         wing: AdiDigitalOut::new(peripherals.adi_h),
     };
 
+    robot
+        .drivetrain
+        .localizer_mut()
+        .log_stats(Some(Duration::from_secs(10)));
+
     robot.compete().await;
 }